@@ -1,4 +1,5 @@
-use clap::{Subcommand, Parser};
+use clap::{Subcommand, Parser, ValueEnum};
+use crate::{export::ExportFormat, import::ImportFormat};
 
 #[derive(Debug, Parser)]
 #[command(name = "senvy")]
@@ -19,7 +20,10 @@ pub enum Commands {
         file: String,
 
         #[arg(value_name = "server url")]
-        remote_url: String
+        remote_url: String,
+
+        #[arg(short, long, value_enum, help = "format of the file with env vars, detected from the extension if not provided")]
+        format: Option<ImportFormat>,
     },
 
     #[command(about = "create a new project entry on the server")]
@@ -32,6 +36,9 @@ pub enum Commands {
 
         #[arg(value_name = "server url")]
         remote_url: String,
+
+        #[arg(short, long, value_enum, help = "format of the file with env vars, detected from the extension if not provided")]
+        format: Option<ImportFormat>,
     },
 
     #[command(about = "delete project entry on the server, blank means current project")]
@@ -50,6 +57,9 @@ pub enum Commands {
 
         #[arg(value_name = "server url")]
         remote_url: Option<String>,
+
+        #[arg(long, help = "pull even if the local version is up to date")]
+        force: bool,
     },
 
     #[command(about = "push env vars to the server, blank name current project")]
@@ -62,8 +72,249 @@ pub enum Commands {
 
         #[arg(value_name = "server url")]
         remote_url: Option<String>,
+
+        #[arg(short, long, value_enum, help = "format of the file with env vars, detected from the extension if not provided")]
+        format: Option<ImportFormat>,
     },
 
     #[command(about = "check if there are new env vars available")]
-    Check {}
+    Check {},
+
+    #[command(about = "show the local config and whether the server has a newer version")]
+    Status {},
+
+    #[command(about = "export env vars from the server in the given format, blank means current project")]
+    Export {
+        #[arg(value_name = "project name")]
+        name: Option<String>,
+
+        #[arg(value_name = "server url")]
+        remote_url: Option<String>,
+
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Bash)]
+        format: ExportFormat,
+
+        #[arg(short, long, value_name = "file to write to instead of stdout")]
+        output: Option<String>,
+
+        #[arg(long, help = "show values of secret vars when printing to stdout")]
+        show_secrets: bool,
+    },
+
+    #[command(about = "list env vars on the server with their metadata, blank means current project")]
+    List {
+        #[arg(value_name = "project name")]
+        name: Option<String>,
+
+        #[arg(value_name = "server url")]
+        remote_url: Option<String>,
+
+        #[arg(long, help = "show values of secret vars")]
+        show_secrets: bool,
+    },
+
+    #[command(about = "change metadata of an env var on the server, blank means current project")]
+    Meta {
+        #[arg(value_name = "var name")]
+        var: String,
+
+        #[arg(long, value_name = "project name")]
+        name: Option<String>,
+
+        #[arg(long, value_name = "server url")]
+        remote_url: Option<String>,
+
+        #[arg(long)]
+        description: Option<String>,
+
+        #[arg(long, value_name = "true/false")]
+        secret: Option<bool>,
+
+        #[arg(long, value_name = "person or team")]
+        owner: Option<String>,
+
+        #[arg(long = "tag", value_name = "tag")]
+        tags: Option<Vec<String>>,
+    },
+
+    #[command(about = "set schema of the project entry from a json or yaml file, without a file shows the current one")]
+    Schema {
+        #[arg(value_name = "path to the schema file")]
+        file: Option<String>,
+
+        #[arg(long, value_name = "project name")]
+        name: Option<String>,
+
+        #[arg(long, value_name = "server url")]
+        remote_url: Option<String>,
+
+        #[arg(long, help = "remove the schema from the project entry")]
+        clear: bool,
+    },
+
+    #[command(about = "check env vars in a local file against the schema on the server, blank means current project")]
+    Validate {
+        #[arg(value_name = "path to the file with env vars")]
+        file: Option<String>,
+
+        #[arg(value_name = "project name")]
+        name: Option<String>,
+
+        #[arg(value_name = "server url")]
+        remote_url: Option<String>,
+
+        #[arg(short, long, value_enum, help = "format of the file with env vars, detected from the extension if not provided")]
+        format: Option<ImportFormat>,
+    },
+
+    #[command(about = "write a template with var names and no values, blank means current project")]
+    Template {
+        #[arg(value_name = "project name")]
+        name: Option<String>,
+
+        #[arg(value_name = "server url")]
+        remote_url: Option<String>,
+
+        #[arg(short, long, value_name = "template file", default_value = ".env.example")]
+        output: String,
+    },
+
+    #[command(about = "set values of env vars on the server without pushing the whole file, blank means current project")]
+    Set {
+        #[arg(value_name = "NAME=value", required = true)]
+        vars: Vec<String>,
+
+        #[arg(long, value_name = "project name")]
+        name: Option<String>,
+
+        #[arg(long, value_name = "server url")]
+        remote_url: Option<String>,
+
+        #[arg(short, long, help = "write the updated vars to the local var file")]
+        write: bool,
+    },
+
+    #[command(about = "remove env vars from the server without pushing the whole file, blank means current project")]
+    Unset {
+        #[arg(value_name = "var name", required = true)]
+        vars: Vec<String>,
+
+        #[arg(long, value_name = "project name")]
+        name: Option<String>,
+
+        #[arg(long, value_name = "server url")]
+        remote_url: Option<String>,
+
+        #[arg(short, long, help = "write the updated vars to the local var file")]
+        write: bool,
+    },
+
+    #[command(about = "keep the local var file in sync with the server, blank means current project")]
+    Watch {
+        #[arg(long, value_name = "project name")]
+        name: Option<String>,
+
+        #[arg(long, value_name = "server url")]
+        remote_url: Option<String>,
+
+        #[arg(long, value_name = "shell command", help = "command run after every change of the local var file")]
+        hook: Option<String>,
+
+        #[arg(long, value_name = "seconds", default_value_t = 30, help = "how often to check for changes when the change stream is unavailable")]
+        interval: u64,
+
+        #[arg(last = true, value_name = "command", help = "command started with the vars in its environment and restarted on every change")]
+        command: Vec<String>,
+    },
+
+    #[command(about = "rename project entry on the server, blank means current project")]
+    Rename {
+        #[arg(value_name = "new project name")]
+        target: String,
+
+        #[arg(long, value_name = "project name")]
+        name: Option<String>,
+
+        #[arg(long, value_name = "server url")]
+        remote_url: Option<String>,
+    },
+
+    #[command(about = "copy project entry on the server under a new name, blank means current project")]
+    Copy {
+        #[arg(value_name = "name of the copy")]
+        target: String,
+
+        #[arg(long, value_name = "project name")]
+        name: Option<String>,
+
+        #[arg(long, value_name = "server url")]
+        remote_url: Option<String>,
+    },
+
+    #[command(about = "execute read, create, update and delete operations on several projects at once")]
+    Batch {
+        #[arg(value_name = "path to the json or yaml file with operations")]
+        file: String,
+
+        #[arg(long, value_name = "server url")]
+        remote_url: Option<String>,
+
+        #[arg(long, help = "apply all operations or none of them")]
+        atomic: bool,
+    },
+
+    #[command(about = "save every project entry on the server to a file")]
+    Backup {
+        #[arg(value_name = "path to the archive file")]
+        file: String,
+
+        #[arg(long, value_name = "server url")]
+        remote_url: Option<String>,
+    },
+
+    #[command(about = "load project entries from a backup archive to the server")]
+    Restore {
+        #[arg(value_name = "path to the archive file")]
+        file: String,
+
+        #[arg(long, value_name = "server url")]
+        remote_url: Option<String>,
+
+        #[arg(long, value_enum, default_value = "fail", help = "what to do with entries that already exist")]
+        policy: RestorePolicy,
+    },
+
+    #[command(about = "print the value of a single env var from the server, blank means current project")]
+    Get {
+        #[arg(value_name = "var name")]
+        var: String,
+
+        #[arg(long, value_name = "project name")]
+        name: Option<String>,
+
+        #[arg(long, value_name = "server url")]
+        remote_url: Option<String>,
+    },
+}
+
+/// what restoring an archive does with entries that already exist
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum RestorePolicy {
+    /// nothing is restored if any of the entries exists
+    Fail,
+    /// existing entries are kept
+    Skip,
+    /// existing entries are replaced
+    Overwrite,
+}
+
+impl RestorePolicy {
+    /// value of the policy query parameter
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RestorePolicy::Fail => "fail",
+            RestorePolicy::Skip => "skip",
+            RestorePolicy::Overwrite => "overwrite",
+        }
+    }
 }
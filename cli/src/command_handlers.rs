@@ -1,25 +1,156 @@
 use crate::{
     config::{Config, write_config, delete_config},
-    utils::{confirm, append_endpoint, get_vars, write_env}
+    utils::{confirm, project_endpoint, get_vars, write_env, author, display_value, format_timestamp, response_error, read_schema, read_batch, read_body, send_failover, first_remote, connect_timeout, request_timeout, JsonBody, AdminAuth},
+    export::{ExportFormat, render, render_template},
+    import::{ImportFormat, env_target},
+    watch::Watcher,
+    args_structure::RestorePolicy
 };
 use anyhow::{Result, Context, anyhow};
 use reqwest::StatusCode;
-use senvy_common::types::{Project, ProjectEntry};
+use senvy_common::{
+    types::{Project, ProjectEntry, ProjectPatch, ProjectTarget, EntryVersion, Var, BatchOperation, BatchResponse, Archive, RestoreResponse, etag},
+    error::{ApiError, ErrorCode}
+};
 use serde_json::{to_string, from_str};
-use std::time::Duration;
+use url::Url;
+use std::{
+    time::Duration,
+    path::Path,
+    fs::OpenOptions,
+    io::{Write, Read}
+};
 
 macro_rules! make_client{
     () => {
         reqwest::Client::builder()
-            .connect_timeout(Duration::from_secs(10))
-            .timeout(Duration::from_secs(5))
+            .default_headers(reqwest::header::HeaderMap::from_iter([
+                (reqwest::header::ACCEPT_ENCODING, reqwest::header::HeaderValue::from_static("gzip")),
+            ]))
+            .connect_timeout(connect_timeout()?)
+            .timeout(request_timeout()?)
             .build()
             .context("building reqwest client")?
     };
 }
 
+// file pulled vars are written to, told to the user when it isn't the imported file
+fn target_path(file: &str, format: Option<ImportFormat>) -> String {
+    let target = env_target(file, format);
+    if target != file {
+        println!("Vars will be pulled to {} since {} is not a dotenv file", target, file);
+    }
+    target
+}
+
+// read entry from the server
+pub(crate) async fn fetch_entry(client: &reqwest::Client, remote_url: &str, name: &str) -> Result<ProjectEntry> {
+    let entry = request_entry(client, remote_url, name, None).await?;
+    // entry is always sent when no version is given
+    entry.ok_or_else(|| anyhow!("server responded with not modified to an unconditional read"))
+        .context("pulling entry from the server")
+}
+
+// read entry from the server only if it changed since the given version
+// None means that the server still has the same version
+pub(crate) async fn fetch_entry_since(client: &reqwest::Client, remote_url: &str, name: &str, version: u128) -> Result<Option<ProjectEntry>> {
+    request_entry(client, remote_url, name, Some(version)).await
+}
+
+async fn request_entry(client: &reqwest::Client, remote_url: &str, name: &str, version: Option<u128>) -> Result<Option<ProjectEntry>> {
+    let endpoint = project_endpoint(remote_url, name, None)?;
+    let mut req = client.get(endpoint);
+    if let Some(version) = version {
+        req = req.header("If-None-Match", etag(version));
+    }
+    let res = send_failover(client, req, remote_url)
+        .await
+        .context("pulling entry from the server")?;
+
+    // check the results
+    let res_status = res.status();
+    if res_status == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let res_body = read_body(res)
+        .await
+        .context("reading response body")?;
+
+    if res_status != StatusCode::OK {
+        return Err(response_error(res_status, &res_body))
+            .context("pulling entry from the server");
+    }
+
+    let entry: ProjectEntry = from_str(&res_body)
+        .context("deserializing config")?;
+    Ok(Some(entry))
+}
+
+// apply a partial change to the entry on the server
+async fn send_patch(client: &reqwest::Client, remote_url: &str, name: &str, project_patch: &ProjectPatch) -> Result<EntryVersion> {
+    let body_str = to_string(project_patch)
+        .context("serializing project patch")?;
+
+    let endpoint = project_endpoint(remote_url, name, None)?;
+    let req = client.patch(endpoint)
+        .json_body(body_str);
+    let res = send_failover(client, req, remote_url)
+        .await
+        .context("updating entry on the server")?;
+
+    let res_status = res.status();
+    let res_body = read_body(res)
+        .await
+        .context("reading response body")?;
+    if res_status != StatusCode::OK {
+        return Err(response_error(res_status, &res_body))
+            .context("updating entry on the server");
+    }
+
+    let version: EntryVersion = from_str(&res_body)
+        .context("parsing version returned from server")?;
+    Ok(version)
+}
+
+// rename or copy the entry on the server, action is the name of the sub resource
+async fn send_move(client: &reqwest::Client, remote_url: &str, name: &str, action: &str, target: &str) -> Result<()> {
+    let body_str = to_string(&ProjectTarget{name: target.to_string()})
+        .context("serializing target name")?;
+
+    let endpoint = project_endpoint(remote_url, name, Some(action))?;
+    let req = client.post(endpoint)
+        .json_body(body_str);
+    let res = send_failover(client, req, remote_url)
+        .await
+        .with_context(|| format!("sending {} to the server", action))?;
+
+    let res_status = res.status();
+    let res_body = read_body(res)
+        .await
+        .context("reading response body")?;
+    if !res_status.is_success() {
+        return Err(response_error(res_status, &res_body))
+            .with_context(|| format!("sending {} to the server", action));
+    }
+    Ok(())
+}
+
+// writes the entry to the local var file after a partial change
+// local config is moved to the new version only if it belongs to the same project
+async fn write_patched(client: &reqwest::Client, conf: Option<Config>, remote_url: &str, name: &str) -> Result<()> {
+    let entry = fetch_entry(client, remote_url, name).await?;
+    if let Some(mut conf) = conf.filter(|c| c.name == name) {
+        conf.last_version = entry.timestamp;
+        conf.path = entry.path.clone();
+        write_config(&conf)?;
+    }
+    write_env(entry)?;
+    println!("Successfully updated local var file");
+    Ok(())
+}
+
 // makes a local config and an entry on the server
-pub async fn init(conf: Option<Config>, name: String, file: String, remote_url: String) -> Result<()> {
+pub async fn init(conf: Option<Config>, name: String, file: String, remote_url: String, format: Option<ImportFormat>) -> Result<()> {
     let mut proceed = true;
 
     // if config exists check if user wants to overwrite it
@@ -28,77 +159,46 @@ pub async fn init(conf: Option<Config>, name: String, file: String, remote_url:
     }
 
     if proceed {
-        // check if project already exists
-        let client = make_client!();
-        let endpoint = append_endpoint(&remote_url, "/exists")?;
-        let res = client.get(endpoint)
-            .body(name.clone())
-            .send()
-            .await
-            .context("checking if entry with the given name exists on the server")?;
-
-        // check the results
-        let res_status = res.status();
-        let res_body = res.text()
-            .await
-            .context("reading response body")?;
-
-        match res_status {
-            StatusCode::OK => {
-                if &res_body == "true" {
-                    println!("Project entry with name \"{}\" already exists, if you want to overwrite it first delete project entry on the server", &name);
-                    return Ok(());
-                }
-            },
-            _ => {
-                println!("Unexpected response from the server, server response: {}", res_body);
-                return Ok(());
-            },
-        }
-
         // parse vars from the file
-        let vars = get_vars(&file)?;
+        let vars = get_vars(&file, format)?;
+        let file = target_path(&file, format);
 
         // body for creating a new entry
         let body = Project{
             name: name.clone(),
             vars,
             path: file.clone(),
+            author: author(),
+            schema: None,
         };
         let body_str = to_string(&body)
             .context("serializing project info")?;
 
-        // push to the server
-        let res = client.post(append_endpoint(&remote_url, "new")?)
-            .body(body_str)
-            .header("Content-Type", "application/json")
-            .send()
+        // push to the server, entry is created only if it doesn't exist already
+        let client = make_client!();
+        let req = client.put(project_endpoint(&remote_url, &name, None)?)
+            .json_body(body_str)
+            .header("If-None-Match", "*");
+        let res = send_failover(&client, req, &remote_url)
             .await
             .context("creating entry on the server")?;
 
         let res_status = res.status();
-        let res_body = res.text()
+        let res_body = read_body(res)
             .await
             .context("reading response body")?;
 
         // if creation of the entry on the server was successfull get back the timestamp
-        let timestamp;
-        match res_status {
-            StatusCode::OK => {
+        let timestamp = match res_status {
+            StatusCode::CREATED => {
                 println!("Successfully created entry on the server");
-                timestamp = res_body.parse::<u128>()
-                    .context("parsing timestamp returned from server")?;
+                let version: EntryVersion = from_str(&res_body)
+                    .context("parsing version returned from server")?;
+                version.timestamp
             },
-            // it is possible that someone made an entry on the server since we checked
-            StatusCode::BAD_REQUEST => {
-                println!("Error making a new entry, server response: {}", res_body);
-                return Ok(());
-            },
-            _ => {
-                println!("Unexpected response from the server, server response: {}", res_body);
-                return Ok(());
-            },
-        }
+            _ => return Err(response_error(res_status, &res_body))
+                .context("creating entry on the server"),
+        };
 
         // write config to the file
         let conf = Config{
@@ -116,74 +216,41 @@ pub async fn init(conf: Option<Config>, name: String, file: String, remote_url:
 }
 
 // new does not update local config, just makes a new entry on the server
-pub async fn new(_: Option<Config>, name: String, file: String, remote_url: String) -> Result<()> {
-    let client = make_client!();
-
-    // check if project exists
-    let endpoint = append_endpoint(&remote_url, "/exists")?;
-    let res = client.get(endpoint)
-        .body(name.clone())
-        .send()
-        .await
-        .context("checking if entry with the given name exists on the server")?;
-
-    // check the results
-    let res_status = res.status();
-    let res_body = res.text()
-        .await
-        .context("reading response body")?;
-
-    match res_status {
-        StatusCode::OK => {
-            if &res_body == "true" {
-                println!("Project entry with name \"{}\" already exists, if you want to overwrite it first delete project entry on the server", &name);
-                return Ok(());
-            }
-        },
-        _ => {
-            println!("Unexpected response from the server, server response: {}", res_body);
-            return Ok(());
-        },
-    }
-
+pub async fn new(_: Option<Config>, name: String, file: String, remote_url: String, format: Option<ImportFormat>) -> Result<()> {
     // parse vars from the file
-    let vars = get_vars(&file)?;
+    let vars = get_vars(&file, format)?;
 
     // body for creating a new entry
     let body = Project{
-        name,
+        name: name.clone(),
         vars,
-        path: file
+        path: target_path(&file, format),
+        author: author(),
+        schema: None,
     };
     let body_str = to_string(&body)
         .context("serializing project info")?;
 
-    // create a new entry
-    let endpoint = append_endpoint(&remote_url, "new")?;
-    let res = client.post(endpoint)
-        .body(body_str)
-        .header("Content-Type", "application/json")
-        .send()
+    // create a new entry only if it doesn't exist already
+    let client = make_client!();
+    let endpoint = project_endpoint(&remote_url, &name, None)?;
+    let req = client.put(endpoint)
+        .json_body(body_str)
+        .header("If-None-Match", "*");
+    let res = send_failover(&client, req, &remote_url)
         .await
         .context("creating entry on the server")?;
 
     // check the results
     let res_status = res.status();
-    let res_body = res.text()
+    let res_body = read_body(res)
         .await
         .context("reading response body")?;
     match res_status {
-        StatusCode::OK =>
+        StatusCode::CREATED =>
             println!("Successfully created entry on the server"),
-        // it is possible that someone made an entry on the server since we checked
-        StatusCode::BAD_REQUEST => {
-            println!("Error making a new entry, server response: {}", res_body);
-            return Ok(());
-        },
-        _ => {
-            println!("Unexpected response from the server, server response: {}", res_body);
-            return Ok(());
-        },
+        _ => return Err(response_error(res_status, &res_body))
+            .context("creating entry on the server"),
     }
 
     Ok(())
@@ -200,41 +267,26 @@ pub async fn delete(conf: Option<Config>, name: Option<String>, remote_url: Opti
     }
 
     // take both provided information and information from config
-    let name = if name.is_some() {
-        name.unwrap()
-    } else {
-        conf.as_ref().unwrap().name.to_owned()
-    };
-    let remote_url = if remote_url.is_some() {
-        remote_url.unwrap()
-    } else {
-        conf.as_ref().unwrap().remote_url.to_owned()
-    };
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
 
     // send delete request
     let client = make_client!();
-    let endpoint = append_endpoint(&remote_url, "delete")?;
-    let res = client.delete(endpoint)
-        .body(name.clone())
-        .send()
+    let endpoint = project_endpoint(&remote_url, &name, None)?;
+    let req = client.delete(endpoint);
+    let res = send_failover(&client, req, &remote_url)
         .await
         .context("deleting project entry on the server")?;
 
     // check the results
     let res_status = res.status();
-    let res_body = res.text()
+    let res_body = read_body(res)
         .await
         .context("reading response body")?;
     match res_status {
-        StatusCode::OK => println!("Successfully deleted project entry from the server"),
-        StatusCode::BAD_REQUEST => {
-            println!("Error deleting entry, server response: {}", res_body);
-            return Ok(());
-        },
-        _ => {
-            println!("Unexpected response from the server, server response: {}", res_body);
-            return Ok(());
-        },
+        StatusCode::NO_CONTENT => println!("Successfully deleted project entry from the server"),
+        _ => return Err(response_error(res_status, &res_body))
+            .context("deleting entry on the server"),
     }
 
     // if there is a local config and if the name of deleted entry is same as the one in config
@@ -251,7 +303,8 @@ pub async fn delete(conf: Option<Config>, name: Option<String>, remote_url: Opti
 
 // pull entry from the server
 // confirm overwriting with user
-pub async fn pull(conf: Option<Config>, name: Option<String>, remote_url: Option<String>)  -> Result<()> {
+// local version is only sent when the local config belongs to the same project and the var file exists
+pub async fn pull(conf: Option<Config>, name: Option<String>, remote_url: Option<String>, force: bool)  -> Result<()> {
     if (name.is_none() || remote_url.is_none()) && conf.is_none() {
         let err = anyhow!("name and remote url are both required when there is no local config")
             .context("gathering information about project");
@@ -259,43 +312,23 @@ pub async fn pull(conf: Option<Config>, name: Option<String>, remote_url: Option
     }
 
     // take both provided information and information from config
-    let name = if name.is_some() {
-        name.unwrap()
-    } else {
-        conf.as_ref().unwrap().name.to_owned()
-    };
-    let remote_url = if remote_url.is_some() {
-        remote_url.unwrap()
-    } else {
-        conf.as_ref().unwrap().remote_url.to_owned()
-    };
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
 
     // send read request
     let client = make_client!();
-    let endpoint = append_endpoint(&remote_url, "read")?;
-    let res = client.get(endpoint)
-        .body(name.clone())
-        .send()
-        .await
-        .context("pulling entry from the server")?;
-
-    // check the results
-    let res_status = res.status();
-    let res_body = res.text()
-        .await
-        .context("reading response body")?;
-
-    match res_status {
-        StatusCode::OK => {},
-        StatusCode::BAD_REQUEST => {
-            println!("Error getting entry from the server: {}", res_body);
-            return Ok(());
-        },
-        _ => {
-                println!("Unexpected response from the server, server response: {}", res_body);
-                return Ok(());
-        },
+    let local_version = conf.as_ref()
+        .filter(|c| c.name == name && Path::new(&c.path).exists() && !force)
+        .map(|c| c.last_version);
+    let entry = match local_version {
+        Some(version) => fetch_entry_since(&client, &remote_url, &name, version).await?,
+        None => Some(fetch_entry(&client, &remote_url, &name).await?),
+    };
+    if entry.is_none() {
+        println!("Local vars are up to date, use --force to pull anyway");
+        return Ok(());
     }
+    let entry = entry.unwrap();
 
     if conf.is_some() {
         let proceed = confirm("Local config already exists, do you want to overwrite it?")?;
@@ -305,9 +338,6 @@ pub async fn pull(conf: Option<Config>, name: Option<String>, remote_url: Option
     }
 
     // write a new config file
-    let entry: ProjectEntry = from_str(&res_body)
-        .context("deserializing config")?;
-
     let config = Config{
         remote_url,
         last_version: entry.timestamp,
@@ -324,7 +354,7 @@ pub async fn pull(conf: Option<Config>, name: Option<String>, remote_url: Option
     Ok(())
 }
 
-pub async fn push(conf: Option<Config>, name: Option<String>, file: Option<String>, remote_url: Option<String>) -> Result<()> {
+pub async fn push(conf: Option<Config>, name: Option<String>, file: Option<String>, remote_url: Option<String>, format: Option<ImportFormat>) -> Result<()> {
     if (file.is_none() || remote_url.is_none()) && conf.is_none() {
         let err = anyhow!("name and remote url are both required when there is no local config")
             .context("gathering information about project");
@@ -332,50 +362,40 @@ pub async fn push(conf: Option<Config>, name: Option<String>, file: Option<Strin
     }
 
     // take both provided information and information from config
-    let name = if name.is_some() {
-        name.unwrap()
-    } else {
-        conf.as_ref().unwrap().name.to_owned()
-    };
-    let remote_url = if remote_url.is_some() {
-        remote_url.unwrap()
-    } else {
-        conf.as_ref().unwrap().remote_url.to_owned()
-    };
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
 
-    let file = if file.is_some() {
-        file.unwrap()
-    } else {
-        conf.as_ref().unwrap().path.to_owned()
-    };
+    let file = file.unwrap_or_else(|| conf.as_ref().unwrap().path.to_owned());
 
-    let vars = get_vars(&file)?;
+    let vars = get_vars(&file, format)?;
     let body = Project{
-        name,
-        path: file,
-        vars
+        name: name.clone(),
+        path: target_path(&file, format),
+        vars,
+        author: author(),
+        schema: None,
     };
     let body_str = to_string(&body)
         .context("serializing project info")?;
 
-    // send the update request
+    // send the update request, entry is replaced only if it exists
     let client = make_client!();
-    let endpoint = append_endpoint(&remote_url, "update")?;
-    let res = client.post(endpoint)
-        .body(body_str)
-        .header("Content-Type", "application/json")
-        .send()
+    let endpoint = project_endpoint(&remote_url, &name, None)?;
+    let req = client.put(endpoint)
+        .json_body(body_str)
+        .header("If-Match", "*");
+    let res = send_failover(&client, req, &remote_url)
         .await
         .context("updating entry on the server")?;
 
     let res_status = res.status();
-    let res_body = res.text()
+    let res_body = read_body(res)
         .await
         .context("reading response body")?;
     match res_status {
         StatusCode::OK => println!("Successfully updated entry on the server"),
-        StatusCode::BAD_REQUEST => println!("Error making a new entry, server response: {}", res_body),
-        _ => println!("Unexpected response from the server, server response: {}", res_body),
+        _ => return Err(response_error(res_status, &res_body))
+            .context("updating entry on the server"),
     }
     Ok(())
 }
@@ -390,35 +410,9 @@ pub async fn check(conf: Option<Config>) -> Result<()> {
 
     // send the read request
     let client = make_client!();
-    let endpoint = append_endpoint(&conf.remote_url, "read")?;
-    let res = client.get(endpoint)
-        .body(conf.name.clone())
-        .send()
-        .await
-        .context("checking entry on the server")?;
+    let new_conf = fetch_entry_since(&client, &conf.remote_url, &conf.name, conf.last_version).await?;
 
-    // check the results
-    let res_status = res.status();
-    let res_body = res.text()
-        .await
-        .context("reading response body")?;
-
-    match res_status {
-        StatusCode::OK => {},
-        StatusCode::BAD_REQUEST => {
-            println!("Error getting entry from the server: {}", res_body);
-            return Ok(());
-        },
-        _ => {
-                println!("Unexpected response from the server, server response: {}", res_body);
-                return Ok(());
-        },
-    }
-
-    let new_conf: ProjectEntry = from_str(&res_body)
-        .context("deserializing config")?;
-
-    if new_conf.timestamp > conf.last_version {
+    if let Some(new_conf) = new_conf.filter(|c| c.timestamp > conf.last_version) {
         println!("New version avaiable");
         let proceed = confirm("Do you want to update local config?")?;
         if proceed {
@@ -439,3 +433,610 @@ pub async fn check(conf: Option<Config>) -> Result<()> {
 
     Ok(())
 }
+
+// show the local config and compare its version with the server without downloading vars
+pub async fn status(conf: Option<Config>) -> Result<()> {
+    if conf.is_none() {
+        println!("Senvy is not initialized in the current directory");
+        return Ok(());
+    }
+    let conf = conf.unwrap();
+
+    println!("Project: {}", conf.name);
+    println!("Server: {}", conf.remote_url);
+    println!("Var file: {}", conf.path);
+    println!("Local version: {}", format_timestamp(conf.last_version));
+
+    let client = make_client!();
+    let entry = fetch_entry_since(&client, &conf.remote_url, &conf.name, conf.last_version).await?;
+    match entry {
+        Some(entry) if entry.timestamp > conf.last_version =>
+            println!("New version available from {}, run pull to update", format_timestamp(entry.timestamp)),
+        Some(entry) if entry.timestamp < conf.last_version =>
+            println!("Server has an older version from {}, entry was probably recreated", format_timestamp(entry.timestamp)),
+        _ => println!("Up to date"),
+    }
+
+    Ok(())
+}
+
+// export vars from the server entry in the given format
+// to the output file if provided otherwise to stdout
+pub async fn export(conf: Option<Config>, name: Option<String>, remote_url: Option<String>, format: ExportFormat, output: Option<String>, show_secrets: bool) -> Result<()> {
+    if (name.is_none() || remote_url.is_none()) && conf.is_none() {
+        let err = anyhow!("name and remote url are both required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+
+    // take both provided information and information from config
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    let client = make_client!();
+    let mut entry = fetch_entry(&client, &remote_url, &name).await?;
+
+    // secrets are only shown when writing to a file or when explicitly asked for
+    if output.is_none() && !show_secrets {
+        for var in entry.vars.iter_mut() {
+            var.value = display_value(var, false);
+        }
+    }
+    let rendered = render(&entry, &name, format)?;
+
+    match output {
+        Some(output) => {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(&output)
+                .context("creating export file")?;
+            file.write_all(rendered.as_bytes())
+                .context("writing vars to the export file")?;
+            println!("Successfully exported vars to {}", output);
+        },
+        None => print!("{}", rendered),
+    }
+
+    Ok(())
+}
+
+// list vars of the entry with their metadata, secret values are masked unless asked for
+pub async fn list(conf: Option<Config>, name: Option<String>, remote_url: Option<String>, show_secrets: bool) -> Result<()> {
+    if (name.is_none() || remote_url.is_none()) && conf.is_none() {
+        let err = anyhow!("name and remote url are both required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+
+    // take both provided information and information from config
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    let client = make_client!();
+    let entry = fetch_entry(&client, &remote_url, &name).await?;
+
+    println!("{} ({}), last changed {}", name, entry.path, format_timestamp(entry.timestamp));
+    for var in entry.vars.iter() {
+        println!("{}={}", var.name, display_value(var, show_secrets));
+        if var.is_secret() {
+            println!("    secret");
+        }
+        if let Some(description) = var.description.as_ref().filter(|d| !d.is_empty()) {
+            println!("    description: {}", description);
+        }
+        if let Some(owner) = var.owner.as_ref().filter(|o| !o.is_empty()) {
+            println!("    owner: {}", owner);
+        }
+        if let Some(tags) = var.tags.as_ref().filter(|t| !t.is_empty()) {
+            println!("    tags: {}", tags.join(", "));
+        }
+        if let Some(modified) = var.modified {
+            match var.modified_by.as_ref() {
+                Some(author) => println!("    modified: {} by {}", format_timestamp(modified), author),
+                None => println!("    modified: {}", format_timestamp(modified)),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// change metadata of a single var in the entry on the server
+// only that var is sent in the set of a patch, its value is unchanged so its modification stamp is kept
+// empty description, owner or tag clear them
+#[allow(clippy::too_many_arguments)]
+pub async fn meta(conf: Option<Config>, var_name: String, name: Option<String>, remote_url: Option<String>,
+    description: Option<String>, secret: Option<bool>, owner: Option<String>, tags: Option<Vec<String>>) -> Result<()> {
+    if (name.is_none() || remote_url.is_none()) && conf.is_none() {
+        let err = anyhow!("name and remote url are both required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+
+    // take both provided information and information from config
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    let client = make_client!();
+    let entry = fetch_entry(&client, &remote_url, &name).await?;
+
+    let var: Option<Var> = entry.vars.into_iter().find(|v| v.name == var_name);
+    if var.is_none() {
+        println!("Var \"{}\" does not exist in project entry \"{}\"", var_name, name);
+        return Ok(());
+    }
+    let mut var = var.unwrap();
+    if description.is_some() {
+        var.description = description;
+    }
+    if secret.is_some() {
+        var.secret = secret;
+    }
+    if owner.is_some() {
+        var.owner = owner;
+    }
+    if tags.is_some() {
+        // a single empty tag clears the tags
+        var.tags = tags.map(|t| t.into_iter().filter(|t| !t.is_empty()).collect());
+    }
+
+    // only the changed var is sent so changes of other vars made meanwhile are kept
+    let body = ProjectPatch{
+        set: vec![var],
+        author: author(),
+        ..Default::default()
+    };
+    send_patch(&client, &remote_url, &name, &body).await?;
+    println!("Successfully updated metadata of \"{}\"", var_name);
+
+    Ok(())
+}
+
+// set the schema of the entry from a file, remove it or print the current one
+pub async fn schema(conf: Option<Config>, file: Option<String>, name: Option<String>, remote_url: Option<String>, clear: bool) -> Result<()> {
+    if (name.is_none() || remote_url.is_none()) && conf.is_none() {
+        let err = anyhow!("name and remote url are both required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+
+    // take both provided information and information from config
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+    let client = make_client!();
+
+    // without a file or clear just show the current schema
+    if file.is_none() && !clear {
+        let entry = fetch_entry(&client, &remote_url, &name).await?;
+        match entry.schema {
+            Some(schema) => println!("{}", serde_json::to_string_pretty(&schema)
+                .context("serializing schema")?),
+            None => println!("Project entry \"{}\" has no schema", name),
+        }
+        return Ok(());
+    }
+
+    // removing the schema is a delete of the schema resource
+    let endpoint = project_endpoint(&remote_url, &name, Some("schema"))?;
+    let res = match file {
+        Some(file) if !clear => {
+            let schema = read_schema(&file)?;
+            let body_str = to_string(&schema)
+                .context("serializing schema")?;
+            let req = client.put(endpoint)
+                .json_body(body_str);
+            send_failover(&client, req, &remote_url)
+                .await
+                .context("setting schema on the server")?
+        },
+        _ => send_failover(&client, client.delete(endpoint), &remote_url)
+            .await
+            .context("removing schema on the server")?,
+    };
+
+    let res_status = res.status();
+    let res_body = read_body(res)
+        .await
+        .context("reading response body")?;
+    match res_status {
+        StatusCode::OK if clear => println!("Successfully removed schema from the server"),
+        StatusCode::OK => println!("Successfully set schema on the server"),
+        _ => return Err(response_error(res_status, &res_body))
+            .context("setting schema on the server"),
+    }
+    Ok(())
+}
+
+// check vars in a local file against the schema of the entry
+pub async fn validate(conf: Option<Config>, file: Option<String>, name: Option<String>, remote_url: Option<String>, format: Option<ImportFormat>) -> Result<()> {
+    if (file.is_none() || name.is_none() || remote_url.is_none()) && conf.is_none() {
+        let err = anyhow!("file, name and remote url are all required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+
+    // take both provided information and information from config
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+    let file = file.unwrap_or_else(|| conf.as_ref().unwrap().path.to_owned());
+
+    let vars = get_vars(&file, format)?;
+
+    let client = make_client!();
+    let schema = fetch_entry(&client, &remote_url, &name).await?.schema;
+    if schema.is_none() {
+        println!("Project entry \"{}\" has no schema", name);
+        return Ok(());
+    }
+
+    // violations end the cli with the schema violation exit status so it can be used in ci
+    let violations = schema.unwrap().validate(&vars);
+    if !violations.is_empty() {
+        let mut err = ApiError::schema_violation(violations);
+        err.message = format!("{} does not follow the schema", file);
+        return Err(anyhow::Error::new(err))
+            .context("validating vars");
+    }
+    println!("{} follows the schema", file);
+    Ok(())
+}
+
+// write a template file with all var names of the entry and no values
+pub async fn template(conf: Option<Config>, name: Option<String>, remote_url: Option<String>, output: String) -> Result<()> {
+    if (name.is_none() || remote_url.is_none()) && conf.is_none() {
+        let err = anyhow!("name and remote url are both required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+
+    // take both provided information and information from config
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    let client = make_client!();
+    let entry = fetch_entry(&client, &remote_url, &name).await?;
+    let rendered = render_template(&entry);
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(&output)
+        .context("creating template file")?;
+    file.write_all(rendered.as_bytes())
+        .context("writing template to the file")?;
+    println!("Successfully wrote template to {}", output);
+
+    Ok(())
+}
+
+// set values of single vars on the server, metadata of existing vars is kept by the server
+pub async fn set(conf: Option<Config>, vars: Vec<String>, name: Option<String>, remote_url: Option<String>, write: bool) -> Result<()> {
+    if (name.is_none() || remote_url.is_none()) && conf.is_none() {
+        let err = anyhow!("name and remote url are both required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+
+    // take both provided information and information from config
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    let mut set = Vec::new();
+    for var in vars {
+        let (var_name, value) = var.split_once('=')
+            .filter(|(n, _)| !n.is_empty())
+            .ok_or_else(|| anyhow!("\"{}\" is not in NAME=value form", var))
+            .context("parsing vars")?;
+        set.push(Var::new(var_name.to_string(), value.to_string()));
+    }
+
+    let body = ProjectPatch{
+        set,
+        author: author(),
+        ..Default::default()
+    };
+    let client = make_client!();
+    send_patch(&client, &remote_url, &name, &body).await?;
+    println!("Successfully set vars on the server");
+
+    if write {
+        write_patched(&client, conf, &remote_url, &name).await?;
+    }
+    Ok(())
+}
+
+// remove single vars on the server
+pub async fn unset(conf: Option<Config>, vars: Vec<String>, name: Option<String>, remote_url: Option<String>, write: bool) -> Result<()> {
+    if (name.is_none() || remote_url.is_none()) && conf.is_none() {
+        let err = anyhow!("name and remote url are both required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+
+    // take both provided information and information from config
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    let body = ProjectPatch{
+        unset: vars,
+        author: author(),
+        ..Default::default()
+    };
+    let client = make_client!();
+    send_patch(&client, &remote_url, &name, &body).await?;
+    println!("Successfully removed vars from the server");
+
+    if write {
+        write_patched(&client, conf, &remote_url, &name).await?;
+    }
+    Ok(())
+}
+
+// print the value of a single var, secrets are printed as well since the value was asked for
+pub async fn get(conf: Option<Config>, var_name: String, name: Option<String>, remote_url: Option<String>) -> Result<()> {
+    if (name.is_none() || remote_url.is_none()) && conf.is_none() {
+        let err = anyhow!("name and remote url are both required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+
+    // take both provided information and information from config
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    let client = make_client!();
+    let entry = fetch_entry(&client, &remote_url, &name).await?;
+    let var = entry.vars.into_iter()
+        .find(|v| v.name == var_name)
+        .ok_or_else(|| anyhow!("var \"{}\" does not exist in project entry \"{}\"", var_name, name))
+        .context("getting var")?;
+    println!("{}", var.value);
+
+    Ok(())
+}
+
+// rename the entry on the server, local config follows it if it belongs to the renamed project
+pub async fn rename(conf: Option<Config>, target: String, name: Option<String>, remote_url: Option<String>) -> Result<()> {
+    if (name.is_none() || remote_url.is_none()) && conf.is_none() {
+        let err = anyhow!("name and remote url are both required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+
+    // take both provided information and information from config
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    let client = make_client!();
+    send_move(&client, &remote_url, &name, "rename", &target).await?;
+    println!("Successfully renamed \"{}\" to \"{}\" on the server", name, target);
+
+    if let Some(mut conf) = conf.filter(|c| c.name == name) {
+        conf.name = target;
+        write_config(&conf)?;
+        println!("Successfully updated local config");
+    }
+    Ok(())
+}
+
+// copy the entry on the server under a new name, local config stays with the original
+pub async fn copy(conf: Option<Config>, target: String, name: Option<String>, remote_url: Option<String>) -> Result<()> {
+    if (name.is_none() || remote_url.is_none()) && conf.is_none() {
+        let err = anyhow!("name and remote url are both required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+
+    // take both provided information and information from config
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    let client = make_client!();
+    send_move(&client, &remote_url, &name, "copy", &target).await?;
+    println!("Successfully copied \"{}\" to \"{}\" on the server", name, target);
+    Ok(())
+}
+
+// send operations on several projects as a single request and print the result of each
+pub async fn batch(conf: Option<Config>, file: String, remote_url: Option<String>, atomic: bool) -> Result<()> {
+    if remote_url.is_none() && conf.is_none() {
+        let err = anyhow!("remote url is required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    let mut batch = read_batch(&file)?;
+    batch.atomic |= atomic;
+    let operations: Vec<(&str, String)> = batch.operations.iter()
+        .map(|o| {
+            let op = match o {
+                BatchOperation::Read{..} => "read",
+                BatchOperation::Create(_) => "create",
+                BatchOperation::Update(_) => "update",
+                BatchOperation::Delete{..} => "delete",
+            };
+            (op, o.project_name().to_string())
+        })
+        .collect();
+
+    let body_str = to_string(&batch)
+        .context("serializing batch")?;
+    let mut endpoint = Url::parse(first_remote(&remote_url))
+        .context("parsing remote url")?;
+    endpoint.path_segments_mut()
+        .map_err(|_| anyhow!("remote url can't have a path"))?
+        .clear()
+        .extend(["v1", "batch"]);
+
+    let client = make_client!();
+    let req = client.post(endpoint)
+        .json_body(body_str);
+    let res = send_failover(&client, req, &remote_url)
+        .await
+        .context("sending batch to the server")?;
+
+    let res_status = res.status();
+    let res_body = read_body(res)
+        .await
+        .context("reading response body")?;
+    if res_status != StatusCode::OK {
+        return Err(response_error(res_status, &res_body))
+            .context("sending batch to the server");
+    }
+    let res: BatchResponse = from_str(&res_body)
+        .context("parsing batch results returned from server")?;
+
+    for ((op, name), result) in operations.iter().zip(res.results.iter()) {
+        match &result.error {
+            Some(err) => println!("{} \"{}\": failed ({}): {}", op, name, result.status, err),
+            None => println!("{} \"{}\": ok ({})", op, name, result.status),
+        }
+    }
+    if res.rolled_back {
+        println!("Batch was rolled back, nothing was applied");
+    }
+
+    // the first failure decides the exit status
+    let failed = res.results.into_iter()
+        .filter_map(|r| r.error)
+        .find(|e| !res.rolled_back || e.code != ErrorCode::Aborted);
+    if let Some(err) = failed {
+        return Err(anyhow::Error::new(err))
+            .context("executing batch");
+    }
+    println!("Successfully executed batch");
+    Ok(())
+}
+
+// save a snapshot of every entry on the server to the file
+pub async fn backup(conf: Option<Config>, file: String, remote_url: Option<String>) -> Result<()> {
+    if remote_url.is_none() && conf.is_none() {
+        let err = anyhow!("remote url is required when there is no local config")
+            .context("gathering information about server");
+        return Err(err);
+    }
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    let mut endpoint = Url::parse(first_remote(&remote_url))
+        .context("parsing remote url")?;
+    endpoint.path_segments_mut()
+        .map_err(|_| anyhow!("remote url can't have a path"))?
+        .clear()
+        .extend(["v1", "admin", "backup"]);
+
+    let client = make_client!();
+    let req = client.get(endpoint)
+        .admin_auth();
+    let res = send_failover(&client, req, &remote_url)
+        .await
+        .context("requesting backup from the server")?;
+
+    let res_status = res.status();
+    let res_body = read_body(res)
+        .await
+        .context("reading response body")?;
+    if res_status != StatusCode::OK {
+        return Err(response_error(res_status, &res_body))
+            .context("requesting backup from the server");
+    }
+    // parsed so a broken archive is never written
+    let archive: Archive = from_str(&res_body)
+        .context("parsing archive returned from server")?;
+
+    let mut output = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(&file)
+        .context("creating archive file")?;
+    output.write_all(to_string(&archive).context("serializing archive")?.as_bytes())
+        .context("writing archive to the file")?;
+    println!("Successfully saved {} projects to {}", archive.entries.len(), file);
+
+    Ok(())
+}
+
+// load every entry of the archive to the server
+pub async fn restore(conf: Option<Config>, file: String, remote_url: Option<String>, policy: RestorePolicy) -> Result<()> {
+    if remote_url.is_none() && conf.is_none() {
+        let err = anyhow!("remote url is required when there is no local config")
+            .context("gathering information about server");
+        return Err(err);
+    }
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    let mut buff = String::new();
+    OpenOptions::new()
+        .read(true)
+        .open(&file)
+        .context("opening archive file")?
+        .read_to_string(&mut buff)
+        .context("reading archive file")?;
+    let archive: Archive = from_str(&buff)
+        .context("parsing archive")?;
+
+    let body_str = to_string(&archive)
+        .context("serializing archive")?;
+    let mut endpoint = Url::parse(first_remote(&remote_url))
+        .context("parsing remote url")?;
+    endpoint.path_segments_mut()
+        .map_err(|_| anyhow!("remote url can't have a path"))?
+        .clear()
+        .extend(["v1", "admin", "restore"]);
+    endpoint.query_pairs_mut()
+        .append_pair("policy", policy.as_str());
+
+    let client = make_client!();
+    let req = client.post(endpoint)
+        .admin_auth()
+        .json_body(body_str);
+    let res = send_failover(&client, req, &remote_url)
+        .await
+        .context("sending archive to the server")?;
+
+    let res_status = res.status();
+    let res_body = read_body(res)
+        .await
+        .context("reading response body")?;
+    if res_status != StatusCode::OK {
+        return Err(response_error(res_status, &res_body))
+            .context("restoring archive");
+    }
+    let res: RestoreResponse = from_str(&res_body)
+        .context("parsing restore results returned from server")?;
+
+    for name in res.skipped.iter() {
+        println!("skipped \"{}\": already exists", name);
+    }
+    println!("Successfully restored {} projects", res.restored.len());
+    Ok(())
+}
+
+// keep pulling the entry on every change until interrupted
+pub async fn watch(conf: Option<Config>, name: Option<String>, remote_url: Option<String>, hook: Option<String>, interval: u64, command: Vec<String>) -> Result<()> {
+    if (name.is_none() || remote_url.is_none()) && conf.is_none() {
+        let err = anyhow!("name and remote url are both required when there is no local config")
+            .context("gathering information about project");
+        return Err(err);
+    }
+
+    // take both provided information and information from config
+    let name = name.unwrap_or_else(|| conf.as_ref().unwrap().name.to_owned());
+    let remote_url = remote_url.unwrap_or_else(|| conf.as_ref().unwrap().remote_url.to_owned());
+
+    // change stream stays open so it can't have a total timeout
+    let stream_client = reqwest::Client::builder()
+        .connect_timeout(connect_timeout()?)
+        .build()
+        .context("building reqwest client")?;
+
+    let mut watcher = Watcher::new(make_client!(), stream_client, conf, name, remote_url);
+    watcher.interval = Duration::from_secs(interval.max(1));
+    watcher.hook = hook;
+    watcher.command = command;
+    watcher.run().await
+}
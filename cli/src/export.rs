@@ -0,0 +1,277 @@
+use anyhow::{Result, Context, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use clap::ValueEnum;
+use senvy_common::{types::ProjectEntry, schema::VarType};
+use serde_derive::Serialize;
+use serde_yaml::{Mapping, Value};
+
+/// formats vars can be exported in
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    /// `export NAME='value'` statements
+    Bash,
+    /// `export NAME='value'` statements
+    Zsh,
+    /// `set -gx NAME 'value'` statements
+    Fish,
+    /// json object with var names as keys
+    Json,
+    /// yaml mapping with var names as keys
+    Yaml,
+    /// file for docker's --env-file
+    Docker,
+    /// file for systemd's EnvironmentFile
+    Systemd,
+    /// kubernetes Secret manifest
+    K8sSecret,
+    /// kubernetes ConfigMap manifest
+    K8sConfigmap,
+}
+
+/// kubernetes manifest holding vars in the data field
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    api_version: &'static str,
+    kind: &'static str,
+    metadata: ManifestMetadata,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    secret_type: Option<&'static str>,
+    data: Mapping,
+}
+
+#[derive(Serialize)]
+struct ManifestMetadata {
+    name: String,
+}
+
+/// renders vars of the entry in the given format
+/// project name is only used as the name of kubernetes objects
+pub fn render(entry: &ProjectEntry, project_name: &str, format: ExportFormat) -> Result<String> {
+    let mut buff = String::new();
+    match format {
+        ExportFormat::Bash | ExportFormat::Zsh => {
+            for var in entry.vars.iter() {
+                buff += &format!("export {}={}\n", var.name, posix_quote(&var.value));
+            }
+        },
+        ExportFormat::Fish => {
+            for var in entry.vars.iter() {
+                buff += &format!("set -gx {} {}\n", var.name, fish_quote(&var.value));
+            }
+        },
+        ExportFormat::Json => {
+            let mut map = serde_json::Map::new();
+            for var in entry.vars.iter() {
+                map.insert(var.name.clone(), serde_json::Value::String(var.value.clone()));
+            }
+            buff = serde_json::to_string_pretty(&map)
+                .context("serializing vars into json")?;
+            buff.push('\n');
+        },
+        ExportFormat::Yaml => {
+            buff = serde_yaml::to_string(&vars_mapping(entry, |v| v.to_string()))
+                .context("serializing vars into yaml")?;
+        },
+        ExportFormat::Docker => {
+            // docker takes everything after '=' literally and has no way of escaping a new line
+            for var in entry.vars.iter() {
+                if var.value.contains('\n') {
+                    return Err(anyhow!("var \"{}\" contains a new line", var.name))
+                        .context("rendering docker env file");
+                }
+                buff += &format!("{}={}\n", var.name, var.value);
+            }
+        },
+        ExportFormat::Systemd => {
+            for var in entry.vars.iter() {
+                buff += &format!("{}={}\n", var.name, systemd_quote(&var.value));
+            }
+        },
+        ExportFormat::K8sSecret | ExportFormat::K8sConfigmap => {
+            let secret = format == ExportFormat::K8sSecret;
+            let manifest = Manifest {
+                api_version: "v1",
+                kind: if secret { "Secret" } else { "ConfigMap" },
+                metadata: ManifestMetadata { name: k8s_name(project_name) },
+                secret_type: if secret { Some("Opaque") } else { None },
+                data: if secret {
+                    vars_mapping(entry, |v| STANDARD.encode(v))
+                } else {
+                    vars_mapping(entry, |v| v.to_string())
+                },
+            };
+            buff = serde_yaml::to_string(&manifest)
+                .context("serializing kubernetes manifest")?;
+        },
+    }
+    Ok(buff)
+}
+
+/// renders a dotenv template with every var from the entry and its schema
+/// values are left out, only schema defaults are filled in
+/// descriptions and rules are written as comments above the var
+pub fn render_template(entry: &ProjectEntry) -> String {
+    // vars from the entry followed by the ones only the schema knows about
+    let mut names: Vec<&str> = entry.vars.iter().map(|v| v.name.as_str()).collect();
+    if let Some(schema) = entry.schema.as_ref() {
+        for rule in schema.vars.iter() {
+            if !names.contains(&rule.name.as_str()) {
+                names.push(&rule.name);
+            }
+        }
+    }
+
+    let mut buff = String::new();
+    for name in names {
+        let var = entry.vars.iter().find(|v| v.name == name);
+        let rule = entry.schema.as_ref().and_then(|s| s.get(name));
+
+        let description = var.and_then(|v| v.description.as_ref())
+            .or_else(|| rule.and_then(|r| r.description.as_ref()))
+            .filter(|d| !d.is_empty());
+        if let Some(description) = description {
+            for line in description.lines() {
+                buff += &format!("# {}\n", line);
+            }
+        }
+
+        let mut notes = Vec::new();
+        if let Some(rule) = rule {
+            if rule.kind != VarType::String {
+                notes.push(format!("type: {}", rule.kind.name()));
+            }
+            if let Some(values) = rule.values.as_ref() {
+                notes.push(format!("one of: {}", values.join(", ")));
+            }
+            if let Some(pattern) = rule.pattern.as_ref() {
+                notes.push(format!("pattern: {}", pattern));
+            }
+            if rule.required {
+                notes.push("required".to_string());
+            }
+        }
+        if var.map(|v| v.is_secret()).unwrap_or(false) {
+            notes.push("secret".to_string());
+        }
+        if !notes.is_empty() {
+            buff += &format!("# {}\n", notes.join(", "));
+        }
+
+        let default = rule.and_then(|r| r.default.as_ref());
+        match default {
+            Some(default) => buff += &format!("{}={}\n", name, dotenv_quote(default)),
+            None => buff += &format!("{}=\n", name),
+        }
+    }
+    buff
+}
+
+/// double quotes the value only if dotenv would not read it as is
+fn dotenv_quote(value: &str) -> String {
+    if value.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:@,".contains(c)) {
+        return value.to_string();
+    }
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// yaml mapping of var names to values, keeps the order of vars
+fn vars_mapping<F: Fn(&str) -> String>(entry: &ProjectEntry, value: F) -> Mapping {
+    let mut map = Mapping::new();
+    for var in entry.vars.iter() {
+        map.insert(Value::String(var.name.clone()), Value::String(value(&var.value)));
+    }
+    map
+}
+
+/// single quotes the value, single quotes inside are closed, escaped and reopened
+fn posix_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// single quotes the value, fish only escapes backslash and single quote inside them
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// double quotes the value escaping characters systemd treats specially
+fn systemd_quote(value: &str) -> String {
+    let mut buff = String::from("\"");
+    for c in value.chars() {
+        if matches!(c, '\\' | '"' | '$' | '`') {
+            buff.push('\\');
+        }
+        buff.push(c);
+    }
+    buff.push('"');
+    buff
+}
+
+/// kubernetes object names are lowercase alphanumerics, '-' and '.'
+fn k8s_name(project_name: &str) -> String {
+    let name: String = project_name.to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '-' })
+        .collect();
+    name.trim_matches(|c| c == '-' || c == '.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use senvy_common::{types::Var, schema::{Schema, SchemaVar}};
+    use super::*;
+
+    fn entry() -> ProjectEntry {
+        ProjectEntry {
+            timestamp: 123,
+            path: "./.env".to_string(),
+            schema: None,
+            vars: vec![
+                Var::new("PORT".to_string(), "8080".to_string()),
+                Var::new("GREETING".to_string(), "it's \"here\"".to_string()),
+            ],
+        }
+    }
+
+    #[test]
+    fn shell() {
+        let res = render(&entry(), "test", ExportFormat::Bash).unwrap();
+        assert_eq!("export PORT='8080'\nexport GREETING='it'\\''s \"here\"'\n", res);
+
+        let res = render(&entry(), "test", ExportFormat::Fish).unwrap();
+        assert_eq!("set -gx PORT '8080'\nset -gx GREETING 'it\\'s \"here\"'\n", res);
+
+        let res = render(&entry(), "test", ExportFormat::Systemd).unwrap();
+        assert_eq!("PORT=\"8080\"\nGREETING=\"it's \\\"here\\\"\"\n", res);
+    }
+
+    #[test]
+    fn template() {
+        let mut entry = entry();
+        entry.vars[0].description = Some("server port".to_string());
+        entry.vars[1].secret = Some(true);
+        entry.schema = Some(Schema {
+            vars: vec![
+                SchemaVar { name: "PORT".to_string(), required: true, kind: VarType::Port, ..Default::default() },
+                SchemaVar {
+                    name: "HOST".to_string(),
+                    default: Some("local host".to_string()),
+                    description: Some("host to bind to".to_string()),
+                    ..Default::default()
+                },
+            ],
+        });
+
+        let res = render_template(&entry);
+        assert_eq!("# server port\n# type: port, required\nPORT=\n# secret\nGREETING=\n# host to bind to\nHOST=\"local host\"\n", res);
+    }
+
+    #[test]
+    fn k8s_secret() {
+        let res = render(&entry(), "My_Project", ExportFormat::K8sSecret).unwrap();
+        let res: serde_yaml::Value = serde_yaml::from_str(&res).unwrap();
+        assert_eq!(res["kind"], "Secret");
+        assert_eq!(res["metadata"]["name"], "my-project");
+        assert_eq!(res["data"]["PORT"], "ODA4MA==");
+    }
+}
@@ -0,0 +1,301 @@
+use std::path::Path;
+use anyhow::{Result, Context, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use clap::ValueEnum;
+use dotenv_parser::parse_dotenv;
+use senvy_common::types::Var;
+
+/// formats vars can be imported from
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ImportFormat {
+    /// `NAME=value` dotenv file
+    Dotenv,
+    /// json object with var names as keys or a list of vars with metadata
+    Json,
+    /// yaml mapping with var names as keys
+    Yaml,
+    /// toml table with var names as keys
+    Toml,
+    /// script with `export NAME=value` statements
+    Shell,
+    /// file for docker's --env-file
+    Docker,
+    /// kubernetes Secret manifest, values in data are base64 decoded
+    K8sSecret,
+}
+
+impl ImportFormat {
+    /// guesses the format from the file extension, defaults to dotenv
+    /// yaml files are checked for being a kubernetes secret when parsed
+    pub fn detect(file: &str) -> Self {
+        let extension = Path::new(file)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        match extension.as_deref() {
+            Some("json") => ImportFormat::Json,
+            Some("yaml") | Some("yml") => ImportFormat::Yaml,
+            Some("toml") => ImportFormat::Toml,
+            Some("sh") | Some("bash") | Some("zsh") => ImportFormat::Shell,
+            Some("list") => ImportFormat::Docker,
+            _ => ImportFormat::Dotenv,
+        }
+    }
+
+    /// pulled vars are written as `NAME=value` lines, only files of these formats can take them
+    pub fn writable(&self) -> bool {
+        matches!(self, ImportFormat::Dotenv | ImportFormat::Docker)
+    }
+}
+
+/// file the vars of an entry are written to when pulled
+/// files in other formats are never overwritten, their vars go to a .env file next to them
+pub fn env_target(file: &str, format: Option<ImportFormat>) -> String {
+    if format.unwrap_or_else(|| ImportFormat::detect(file)).writable() {
+        return file.to_string();
+    }
+    Path::new(file)
+        .with_file_name(".env")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// parses vars from the contents of a file in the given format
+pub fn parse_vars(content: &str, format: ImportFormat) -> Result<Vec<Var>> {
+    match format {
+        ImportFormat::Dotenv => {
+            let vars_map = parse_dotenv(content)
+                .map_err(|e| anyhow!(e))
+                .context("parsing env vars")?;
+            Ok(vars_map.into_iter()
+                .map(|(name, value)| Var::new(name, value))
+                .collect())
+        },
+        ImportFormat::Json => {
+            let data: serde_json::Value = serde_json::from_str(content)
+                .context("parsing json")?;
+
+            // list of vars can carry metadata along with values
+            if data.is_array() {
+                let vars: Vec<Var> = serde_json::from_value(data)
+                    .context("parsing json list of vars")?;
+                return Ok(vars);
+            }
+
+            let data = data.as_object()
+                .ok_or_else(|| anyhow!("expected an object with var names as keys or a list of vars"))
+                .context("parsing json")?;
+
+            let mut vars = Vec::new();
+            for (name, value) in data.iter() {
+                let value = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    serde_json::Value::Number(n) => n.to_string(),
+                    serde_json::Value::Bool(b) => b.to_string(),
+                    _ => return Err(anyhow!("value of \"{}\" is not a string, number or bool", name))
+                        .context("parsing json"),
+                };
+                vars.push(Var::new(name.clone(), value));
+            }
+            Ok(vars)
+        },
+        ImportFormat::Yaml | ImportFormat::K8sSecret => {
+            let data: serde_yaml::Value = serde_yaml::from_str(content)
+                .context("parsing yaml")?;
+            if format == ImportFormat::K8sSecret || data["kind"] == "Secret" {
+                return parse_k8s_secret(&data);
+            }
+            yaml_vars(&data).context("parsing yaml")
+        },
+        ImportFormat::Toml => {
+            let data: toml::Table = toml::from_str(content)
+                .context("parsing toml")?;
+
+            let mut vars = Vec::new();
+            for (name, value) in data.into_iter() {
+                let value = match value {
+                    toml::Value::String(s) => s,
+                    toml::Value::Integer(i) => i.to_string(),
+                    toml::Value::Float(f) => f.to_string(),
+                    toml::Value::Boolean(b) => b.to_string(),
+                    _ => return Err(anyhow!("value of \"{}\" is not a string, number or bool", name))
+                        .context("parsing toml"),
+                };
+                vars.push(Var::new(name, value));
+            }
+            Ok(vars)
+        },
+        ImportFormat::Shell => {
+            let mut vars = Vec::new();
+            for (i, line) in content.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+                let (name, value) = line.split_once('=')
+                    .ok_or_else(|| anyhow!("line {} is not an assignment", i + 1))
+                    .context("parsing shell script")?;
+                let value = shell_unquote(value)
+                    .with_context(|| format!("parsing value on line {}", i + 1))?;
+                vars.push(Var::new(name.trim().to_string(), value));
+            }
+            Ok(vars)
+        },
+        ImportFormat::Docker => {
+            // docker takes everything after '=' literally
+            // lines with only the name take the value from the current environment
+            let mut vars = Vec::new();
+            for line in content.lines() {
+                let trimmed = line.trim_start();
+                if trimmed.is_empty() || trimmed.starts_with('#') {
+                    continue;
+                }
+                match trimmed.split_once('=') {
+                    Some((name, value)) => vars.push(Var::new(name.to_string(), value.to_string())),
+                    None => {
+                        let name = trimmed.trim_end();
+                        if let Ok(value) = std::env::var(name) {
+                            vars.push(Var::new(name.to_string(), value));
+                        }
+                    },
+                }
+            }
+            Ok(vars)
+        },
+    }
+}
+
+/// vars from a yaml mapping of scalars
+fn yaml_vars(data: &serde_yaml::Value) -> Result<Vec<Var>> {
+    let data = data.as_mapping()
+        .ok_or_else(|| anyhow!("expected a mapping with var names as keys"))?;
+
+    let mut vars = Vec::new();
+    for (name, value) in data.iter() {
+        let name = name.as_str()
+            .ok_or_else(|| anyhow!("var names have to be strings"))?;
+        let value = match value {
+            serde_yaml::Value::String(s) => s.clone(),
+            serde_yaml::Value::Number(n) => n.to_string(),
+            serde_yaml::Value::Bool(b) => b.to_string(),
+            _ => return Err(anyhow!("value of \"{}\" is not a string, number or bool", name)),
+        };
+        vars.push(Var::new(name.to_string(), value));
+    }
+    Ok(vars)
+}
+
+/// vars from base64 encoded data and plain stringData of a kubernetes secret
+fn parse_k8s_secret(data: &serde_yaml::Value) -> Result<Vec<Var>> {
+    let mut vars = Vec::new();
+    if !data["data"].is_null() {
+        for var in yaml_vars(&data["data"]).context("parsing secret data")? {
+            let value = STANDARD.decode(var.value.trim())
+                .with_context(|| format!("decoding base64 value of \"{}\"", var.name))?;
+            let value = String::from_utf8(value)
+                .with_context(|| format!("value of \"{}\" is not utf-8", var.name))?;
+            vars.push(Var::new(var.name, value));
+        }
+    }
+    if !data["stringData"].is_null() {
+        vars.extend(yaml_vars(&data["stringData"]).context("parsing secret stringData")?);
+    }
+    Ok(vars)
+}
+
+/// removes shell quoting from a single word, only a comment can follow it
+/// single quotes are literal, double quotes and bare words allow backslash escapes
+fn shell_unquote(value: &str) -> Result<String> {
+    let mut buff = String::new();
+    let mut chars = value.trim().chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => loop {
+                match chars.next() {
+                    Some('\'') => break,
+                    Some(c) => buff.push(c),
+                    None => return Err(anyhow!("unterminated single quote")),
+                }
+            },
+            '"' => loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(c) if matches!(c, '"' | '\\' | '$' | '`') => buff.push(c),
+                        Some(c) => {
+                            buff.push('\\');
+                            buff.push(c);
+                        },
+                        None => return Err(anyhow!("unterminated double quote")),
+                    },
+                    Some(c) => buff.push(c),
+                    None => return Err(anyhow!("unterminated double quote")),
+                }
+            },
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    buff.push(c);
+                }
+            },
+            // anything but a comment after the word would be cut off, like a second assignment
+            c if c.is_whitespace() => {
+                let rest: String = chars.collect();
+                let rest = rest.trim_start();
+                if !rest.is_empty() && !rest.starts_with('#') {
+                    return Err(anyhow!("unquoted whitespace before \"{}\", quote the value and keep one assignment per line", rest));
+                }
+                break;
+            },
+            c => buff.push(c),
+        }
+    }
+    Ok(buff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target() {
+        assert_eq!("config/.env.local", env_target("config/.env.local", None));
+        assert_eq!("prod.list", env_target("prod.list", None));
+        assert_eq!("config/.env", env_target("config/vars.json", None));
+        assert_eq!(".env", env_target("secret.yaml", None));
+        assert_eq!(".env", env_target("vars.txt", Some(ImportFormat::Toml)));
+    }
+
+    #[test]
+    fn shell() {
+        let content = "#!/bin/sh\nexport PORT=8080\nexport GREETING='it'\\''s \"here\"'\nHOST=\"local\\\"host\"\n";
+        let vars = parse_vars(content, ImportFormat::Shell).unwrap();
+        assert_eq!(vec![
+            Var::new("PORT".to_string(), "8080".to_string()),
+            Var::new("GREETING".to_string(), "it's \"here\"".to_string()),
+            Var::new("HOST".to_string(), "local\"host".to_string()),
+        ], vars);
+    }
+
+    #[test]
+    fn shell_whitespace() {
+        let vars = parse_vars("PORT=8080 # web port\n", ImportFormat::Shell).unwrap();
+        assert_eq!(vec![Var::new("PORT".to_string(), "8080".to_string())], vars);
+
+        // second assignment and unquoted words are rejected instead of dropped
+        for content in ["A=0\nexport A=1 B=2\n", "A=0\nGREETING=hello world\n"] {
+            let err = parse_vars(content, ImportFormat::Shell).unwrap_err();
+            assert!(err.to_string().contains("line 2"));
+        }
+    }
+
+    #[test]
+    fn k8s_secret() {
+        let content = "apiVersion: v1\nkind: Secret\nmetadata:\n  name: test\ndata:\n  PORT: ODA4MA==\nstringData:\n  HOST: localhost\n";
+        let vars = parse_vars(content, ImportFormat::Yaml).unwrap();
+        assert_eq!(vec![
+            Var::new("PORT".to_string(), "8080".to_string()),
+            Var::new("HOST".to_string(), "localhost".to_string()),
+        ], vars);
+    }
+}
@@ -1,9 +1,15 @@
 pub mod args_structure;
 pub mod config;
 pub mod command_handlers;
+pub mod export;
+pub mod import;
 pub mod utils;
+pub mod watch;
 
+use std::process;
 use clap::Parser;
+use senvy_common::error::ApiError;
+use utils::{explain_error, explain_network_error, exit_status};
 use args_structure::Commands;
 use command_handlers::*;
 
@@ -18,17 +24,49 @@ async fn main() {
 
     let config = config.unwrap();
     let res = match args.command {
-        Commands::Init{name, file, remote_url} => init(config, name, file, remote_url).await,
-        Commands::New{name, file, remote_url} => new(config, name, file, remote_url).await,
+        Commands::Init{name, file, remote_url, format} => init(config, name, file, remote_url, format).await,
+        Commands::New{name, file, remote_url, format} => new(config, name, file, remote_url, format).await,
         Commands::Delete{name, remote_url} => delete(config, name, remote_url).await,
-        Commands::Pull{name, remote_url} => pull(config, name, remote_url).await,
-        Commands::Push{name, file, remote_url} => push(config, name, file, remote_url).await,
+        Commands::Pull{name, remote_url, force} => pull(config, name, remote_url, force).await,
+        Commands::Push{name, file, remote_url, format} => push(config, name, file, remote_url, format).await,
         Commands::Check{} => check(config).await,
+        Commands::Status{} => status(config).await,
+        Commands::Export{name, remote_url, format, output, show_secrets} => export(config, name, remote_url, format, output, show_secrets).await,
+        Commands::List{name, remote_url, show_secrets} => list(config, name, remote_url, show_secrets).await,
+        Commands::Meta{var, name, remote_url, description, secret, owner, tags} =>
+            meta(config, var, name, remote_url, description, secret, owner, tags).await,
+        Commands::Schema{file, name, remote_url, clear} => schema(config, file, name, remote_url, clear).await,
+        Commands::Validate{file, name, remote_url, format} => validate(config, file, name, remote_url, format).await,
+        Commands::Template{name, remote_url, output} => template(config, name, remote_url, output).await,
+        Commands::Set{vars, name, remote_url, write} => set(config, vars, name, remote_url, write).await,
+        Commands::Unset{vars, name, remote_url, write} => unset(config, vars, name, remote_url, write).await,
+        Commands::Get{var, name, remote_url} => get(config, var, name, remote_url).await,
+        Commands::Rename{target, name, remote_url} => rename(config, target, name, remote_url).await,
+        Commands::Copy{target, name, remote_url} => copy(config, target, name, remote_url).await,
+        Commands::Batch{file, remote_url, atomic} => batch(config, file, remote_url, atomic).await,
+        Commands::Backup{file, remote_url} => backup(config, file, remote_url).await,
+        Commands::Restore{file, remote_url, policy} => restore(config, file, remote_url, policy).await,
+        Commands::Watch{name, remote_url, hook, interval, command} => watch(config, name, remote_url, hook, interval, command).await,
     };
 
     if res.is_err() {
         let err = res.err().unwrap();
         println!("Error encountered while: {}", err);
+
+        // errors returned by the server are explained by their code
+        let api_err = err.chain().find_map(|e| e.downcast_ref::<ApiError>());
+        if let Some(api_err) = api_err {
+            println!("\t{}", explain_error(api_err).replace('\n', "\n\t"));
+            process::exit(exit_status(api_err.code));
+        }
+        // network errors are explained instead of printing the whole chain of reqwest errors
+        let net_err = err.chain().find_map(|e| e.downcast_ref::<reqwest::Error>());
+        if let Some(net_err) = net_err {
+            let (msg, status) = explain_network_error(net_err);
+            println!("\t{}", msg.replace('\n', "\n\t"));
+            process::exit(status);
+        }
         println!("\t{}", err.root_cause());
+        process::exit(1);
     }
 }
@@ -1,11 +1,33 @@
 use std::{
     io::{stdin, stdout, Write, Read},
-    fs::OpenOptions
+    fs::OpenOptions,
+    time::Duration
 };
 use anyhow::{Result, Context, anyhow};
-use dotenv_parser::parse_dotenv;
-use senvy_common::types::{Var, ProjectEntry};
+use senvy_common::{
+    types::{Var, ProjectEntry, Batch},
+    schema::Schema,
+    error::{ApiError, ErrorCode}
+};
+use reqwest::{Client, Method, StatusCode, Request, RequestBuilder, Response, header};
+use flate2::{Compression, write::GzEncoder, read::GzDecoder};
 use url::Url;
+use crate::import::{ImportFormat, parse_vars};
+
+/// request bodies larger than this are sent compressed
+const COMPRESS_THRESHOLD: usize = 1024;
+/// default time in seconds connecting to the server can take
+const CONNECT_TIMEOUT: u64 = 10;
+/// default time in seconds a whole request can take
+const REQUEST_TIMEOUT: u64 = 5;
+/// default number of times a failed read is sent again
+const RETRIES: u64 = 3;
+/// times a request rejected for too many requests is sent again
+const RATE_LIMIT_RETRIES: u32 = 3;
+/// wait before the first retry when the server doesn't say how long to wait, doubled on every retry
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+/// longest wait before a retry, even if the server asks for more
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
 
 /// confirm with user via stdio
 pub fn confirm(msg: &str) -> Result<bool> {
@@ -19,11 +41,11 @@ pub fn confirm(msg: &str) -> Result<bool> {
         buff.clear();
         stdin.read_line(&mut buff).context("reading user input")?;
 
-        if buff.len() == 0 {
+        if buff.is_empty() {
             continue;
         }else {
             buff = buff.to_uppercase();
-            if buff.chars().nth(0).unwrap() == 'N' {
+            if buff.starts_with('N') {
                 return Ok(false);
             }else {
                 return Ok(true);
@@ -32,16 +54,27 @@ pub fn confirm(msg: &str) -> Result<bool> {
     }
 }
 
-/// append endpoint to a given url
-pub fn append_endpoint(url: &String, endpoint: &str) -> Result<String> {
-    let mut parsed_url = Url::parse(&url)
+/// url of the project resource on the server, optionally of a sub resource
+/// name is percent encoded so it always stays a single path segment
+pub fn project_endpoint(url: &str, name: &str, sub_resource: Option<&str>) -> Result<String> {
+    let mut parsed_url = Url::parse(first_remote(url))
         .context("parsing remote url")?;
-    parsed_url.set_path(endpoint);
+    {
+        let mut segments = parsed_url.path_segments_mut()
+            .map_err(|_| anyhow!("remote url can't have a path"))
+            .context("parsing remote url")?;
+        segments.clear().extend(["v1", "projects", name]);
+        if let Some(sub_resource) = sub_resource {
+            segments.push(sub_resource);
+        }
+    }
     Ok(parsed_url.as_str().to_string())
 }
 
-/// given the file path to the dot file, parse vars
-pub fn get_vars(file: &str) -> Result<Vec<Var>> {
+/// given the file path to the file with vars, parse vars
+/// format is detected from the file extension if not provided
+pub fn get_vars(file: &str, format: Option<ImportFormat>) -> Result<Vec<Var>> {
+    let format = format.unwrap_or_else(|| ImportFormat::detect(file));
     let mut file = OpenOptions::new()
         .read(true)
         .open(file)
@@ -51,20 +84,325 @@ pub fn get_vars(file: &str) -> Result<Vec<Var>> {
     file.read_to_string(&mut lines)
         .context("reading vars")?;
 
-    let mut vars: Vec<Var> = Vec::new();
-    let vars_map = parse_dotenv(&lines)
-        .map_err(|e| anyhow!(e))
-        .context("parsing env vars")?;
+    parse_vars(&lines, format)
+}
+
+/// reads a schema from a json or yaml file
+pub fn read_schema(file: &str) -> Result<Schema> {
+    let mut buff = String::new();
+    OpenOptions::new()
+        .read(true)
+        .open(file)
+        .context("opening schema file")?
+        .read_to_string(&mut buff)
+        .context("reading schema file")?;
+
+    match ImportFormat::detect(file) {
+        ImportFormat::Yaml => serde_yaml::from_str(&buff)
+            .context("parsing yaml schema"),
+        _ => serde_json::from_str(&buff)
+            .context("parsing json schema"),
+    }
+}
+
+/// reads batch operations from a json or yaml file
+pub fn read_batch(file: &str) -> Result<Batch> {
+    let mut buff = String::new();
+    OpenOptions::new()
+        .read(true)
+        .open(file)
+        .context("opening batch file")?
+        .read_to_string(&mut buff)
+        .context("reading batch file")?;
+
+    match ImportFormat::detect(file) {
+        ImportFormat::Yaml => serde_yaml::from_str(&buff)
+            .context("parsing yaml batch"),
+        _ => serde_json::from_str(&buff)
+            .context("parsing json batch"),
+    }
+}
+
+/// sending json request bodies, large ones are compressed with gzip
+pub trait JsonBody {
+    fn json_body(self, body: String) -> Self;
+}
+
+impl JsonBody for RequestBuilder {
+    fn json_body(self, body: String) -> Self {
+        let req = self.header(header::CONTENT_TYPE, "application/json");
+        if body.len() < COMPRESS_THRESHOLD {
+            return req.body(body);
+        }
+        // compressing into memory can't really fail, the body is sent as it is if it does
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        match encoder.write_all(body.as_bytes()).and_then(|_| encoder.finish()) {
+            Ok(compressed) => req.header(header::CONTENT_ENCODING, "gzip").body(compressed),
+            Err(_) => req.body(body),
+        }
+    }
+}
+
+/// remote url can hold several servers separated by commas, e.g. a primary and its replicas
+pub fn remote_urls(remote_url: &str) -> Vec<&str> {
+    remote_url.split(',')
+        .map(|u| u.trim())
+        .filter(|u| !u.is_empty())
+        .collect()
+}
+
+/// server requests are built for, the rest are only tried when it fails
+pub fn first_remote(remote_url: &str) -> &str {
+    remote_urls(remote_url).first().copied().unwrap_or(remote_url)
+}
+
+/// sends the request to the servers of the remote url in order until one of them takes it
+/// next server is tried when the server can't be reached, is unavailable or is a replica refusing a write
+/// reads are sent again to all servers with a growing wait in between while they fail in a way that might pass
+pub async fn send_failover(client: &Client, req: RequestBuilder, remote_url: &str) -> Result<Response> {
+    let req = req.build()?;
+    // only requests that change nothing are safe to send again after a timeout
+    let retries = match *req.method() {
+        Method::GET | Method::HEAD => retries()?,
+        _ => 0,
+    };
+
+    let mut delay = RETRY_DELAY;
+    for _ in 0..retries {
+        let Some(attempt) = req.try_clone() else {
+            break;
+        };
+        let res = send_servers(client, attempt, remote_url).await;
+        let transient = match &res {
+            Ok(res) => matches!(res.status(), StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT),
+            Err(err) => err.is_connect() || err.is_timeout(),
+        };
+        if !transient {
+            return Ok(res?);
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+    Ok(send_servers(client, req, remote_url).await?)
+}
+
+/// sends the request to the servers of the remote url in order, response or error of the last one is returned
+async fn send_servers(client: &Client, mut req: Request, remote_url: &str) -> reqwest::Result<Response> {
+    // request is built for the first server, only the path and query are moved to the next ones
+    for next in remote_urls(remote_url).into_iter().skip(1) {
+        let Ok(mut next) = Url::parse(next) else {
+            continue;
+        };
+        // bodies that can't be cloned can only be sent once
+        let Some(attempt) = req.try_clone() else {
+            break;
+        };
+        let res = execute_limited(client, attempt).await;
+        let failed = match &res {
+            Ok(res) => res.status() == StatusCode::SERVICE_UNAVAILABLE || res.status() == StatusCode::METHOD_NOT_ALLOWED,
+            Err(err) => err.is_connect(),
+        };
+        if !failed {
+            return res;
+        }
+        next.set_path(req.url().path());
+        next.set_query(req.url().query());
+        *req.url_mut() = next;
+    }
+    execute_limited(client, req).await
+}
+
+/// executes the request, sending it again while the server rejects it for too many requests
+/// server is waited for as long as it asks in the retry-after header
+async fn execute_limited(client: &Client, req: Request) -> reqwest::Result<Response> {
+    let mut delay = RETRY_DELAY;
+    for _ in 0..RATE_LIMIT_RETRIES {
+        let Some(attempt) = req.try_clone() else {
+            break;
+        };
+        let res = client.execute(attempt).await?;
+        if res.status() != StatusCode::TOO_MANY_REQUESTS {
+            return Ok(res);
+        }
+        let wait = res.headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok()?.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(delay);
+        tokio::time::sleep(wait.min(MAX_RETRY_DELAY)).await;
+        delay *= 2;
+    }
+    client.execute(req).await
+}
+
+/// token of the admin endpoints of the server, taken from the SENVY_ADMIN_TOKEN env var
+pub trait AdminAuth {
+    fn admin_auth(self) -> Self;
+}
+
+impl AdminAuth for RequestBuilder {
+    fn admin_auth(self) -> Self {
+        match std::env::var("SENVY_ADMIN_TOKEN") {
+            Ok(token) if !token.is_empty() => self.bearer_auth(token),
+            _ => self,
+        }
+    }
+}
+
+/// number set by the env var, default if it isn't set
+fn env_number(name: &str, default: u64) -> Result<u64> {
+    match std::env::var(name) {
+        Ok(value) => value.trim().parse::<u64>()
+            .with_context(|| format!("malformed {} env var", name)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// how long connecting to the server can take
+pub fn connect_timeout() -> Result<Duration> {
+    env_number("SENVY_CONNECT_TIMEOUT", CONNECT_TIMEOUT).map(Duration::from_secs)
+}
+
+/// how long a whole request can take
+pub fn request_timeout() -> Result<Duration> {
+    env_number("SENVY_TIMEOUT", REQUEST_TIMEOUT).map(Duration::from_secs)
+}
+
+/// times a read is sent again after failing in a way that might pass
+fn retries() -> Result<u64> {
+    env_number("SENVY_RETRIES", RETRIES)
+}
+
+/// body of a response, gzip compressed bodies are decompressed
+pub async fn read_body(res: Response) -> Result<String> {
+    let gzip = res.headers()
+        .get(header::CONTENT_ENCODING)
+        .map(|v| v == "gzip")
+        .unwrap_or(false);
+    let bytes = res.bytes().await?;
+    if !gzip {
+        return Ok(String::from_utf8_lossy(&bytes).into_owned());
+    }
+
+    let mut body = String::new();
+    GzDecoder::new(bytes.as_ref())
+        .read_to_string(&mut body)
+        .context("decompressing response body")?;
+    Ok(body)
+}
+
+/// error from an unsuccessful response of the server
+/// bodies that are not an api error, e.g. from a proxy, are kept as the message
+pub fn response_error(status: StatusCode, body: &str) -> anyhow::Error {
+    let err = serde_json::from_str::<ApiError>(body)
+        .unwrap_or_else(|_| match status.is_server_error() {
+            true => ApiError::new(ErrorCode::Internal, &format!("server error ({}): {}", status, body)),
+            false => ApiError::new(ErrorCode::Unknown, &format!("unexpected response ({}): {}", status, body)),
+        });
+    anyhow::Error::new(err)
+}
+
+/// what went wrong while talking to the server and the exit status for it
+pub fn explain_network_error(err: &reqwest::Error) -> (String, i32) {
+    let server = err.url()
+        .and_then(|u| u.host_str().map(|h| format!(" {}", h)))
+        .unwrap_or_default();
+    if err.is_timeout() {
+        let msg = format!("timed out waiting for the server{}\nSENVY_TIMEOUT and SENVY_CONNECT_TIMEOUT set longer timeouts in seconds", server);
+        return (msg, 14);
+    }
+    if err.is_connect() {
+        let msg = format!("server{} is unreachable\ncheck the server url, that the server is running and your connection", server);
+        return (msg, 13);
+    }
+    let cause = std::error::Error::source(err)
+        .map(|e| e.to_string())
+        .unwrap_or_else(|| err.to_string());
+    (format!("connection to the server{} failed: {}", server, cause), 1)
+}
+
+/// what went wrong and how to fix it, based on the code of the error
+pub fn explain_error(err: &ApiError) -> String {
+    let hint = match err.code {
+        ErrorCode::InvalidName => "project names can't be empty, start with '.' or contain '/' or '\\'",
+        ErrorCode::NotFound => "project entry does not exist on the server, create it with init or new",
+        ErrorCode::AlreadyExists => "project entry already exists on the server, if you want to overwrite it first delete it",
+        ErrorCode::SchemaViolation => "fix the vars or change the schema of the project entry",
+        ErrorCode::MalformedJson => "server could not read the request, server and cli versions might not match",
+        ErrorCode::Internal => "server error, the server failed to handle the request, check the server logs",
+        ErrorCode::Unavailable => "server can't handle requests right now, try again later",
+        ErrorCode::Aborted => "nothing from the batch was applied, fix the failed operation and send it again",
+        ErrorCode::PayloadTooLarge => "entry is larger than the server allows, MAX_BODY_SIZE of the server can be raised",
+        ErrorCode::ReadOnly => "server is a read-only replica, add the url of the primary to the remote urls",
+        ErrorCode::RateLimited => "too many requests were sent to the server, wait a bit before trying again",
+        ErrorCode::Forbidden => "admin commands need the ADMIN_TOKEN of the server in the SENVY_ADMIN_TOKEN env var",
+        ErrorCode::Unknown => return err.to_string(),
+    };
+    format!("{}\n{}", err, hint)
+}
+
+/// exit status of the cli for errors returned by the server
+pub fn exit_status(code: ErrorCode) -> i32 {
+    match code {
+        ErrorCode::InvalidName => 2,
+        ErrorCode::NotFound => 3,
+        ErrorCode::AlreadyExists => 4,
+        ErrorCode::SchemaViolation => 5,
+        ErrorCode::MalformedJson => 6,
+        ErrorCode::Internal => 7,
+        ErrorCode::Unavailable => 8,
+        ErrorCode::Aborted => 9,
+        ErrorCode::PayloadTooLarge => 10,
+        ErrorCode::ReadOnly => 11,
+        ErrorCode::RateLimited => 12,
+        ErrorCode::Forbidden => 15,
+        ErrorCode::Unknown => 1,
+    }
+}
+
+/// name of the current user, sent to the server as the author of changes
+pub fn author() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|u| !u.is_empty())
+}
 
-    for (key, value) in vars_map.into_iter() {
-        vars.push(Var{name: key, value});
+/// value of the var to be shown to the user, secrets are masked unless asked for
+pub fn display_value(var: &Var, show_secrets: bool) -> String {
+    if var.is_secret() && !show_secrets {
+        return "********".to_string();
     }
+    var.value.clone()
+}
+
+/// formats a timestamp in nanoseconds since the unix epoch as utc date and time
+pub fn format_timestamp(timestamp: u128) -> String {
+    let secs = (timestamp / 1_000_000_000) as i64;
+    let (days, secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
 
-    Ok(vars)
+    // converting days since the epoch to a civil date
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year, month, day, secs / 3600, secs % 3600 / 60, secs % 60)
 }
 
 /// writes the env vars into the given file
+/// files in formats other than dotenv are never overwritten
 pub fn write_env(data: ProjectEntry) -> Result<()> {
+    if !ImportFormat::detect(&data.path).writable() {
+        return Err(anyhow!("{} is not a dotenv file, vars would overwrite it", data.path))
+            .context("writing vars to the file");
+    }
     let mut file = OpenOptions::new()
         .create(true)
         .truncate(true)
@@ -80,9 +418,49 @@ pub fn write_env(data: ProjectEntry) -> Result<()> {
         buff.push('\n');
     }
 
-    file.write_all(&buff.as_bytes())
+    file.write_all(buff.as_bytes())
         .context("writing vars to the file")?;
 
     Ok(())
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_error() {
+        let err = response_error(StatusCode::NOT_FOUND, r#"{"code":"not_found","message":"project does not exist"}"#);
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(ErrorCode::NotFound, err.code);
+        assert_eq!(3, exit_status(err.code));
+
+        let err = response_error(StatusCode::NOT_FOUND, "not found");
+        assert_eq!(ErrorCode::Unknown, err.downcast_ref::<ApiError>().unwrap().code);
+
+        // server errors without an api error, e.g. from a proxy, are still server errors
+        let err = response_error(StatusCode::BAD_GATEWAY, "bad gateway");
+        assert_eq!(ErrorCode::Internal, err.downcast_ref::<ApiError>().unwrap().code);
+    }
+
+    #[test]
+    fn endpoint() {
+        let res = project_endpoint("http://localhost:8080", "test project", None).unwrap();
+        assert_eq!("http://localhost:8080/v1/projects/test%20project", res);
+
+        let res = project_endpoint("http://localhost:8080/old", "a/b", Some("schema")).unwrap();
+        assert_eq!("http://localhost:8080/v1/projects/a%2Fb/schema", res);
+
+        // endpoints are built for the first of several servers
+        let res = project_endpoint("http://primary:8080, http://replica:8080", "test", None).unwrap();
+        assert_eq!("http://primary:8080/v1/projects/test", res);
+        assert_eq!(vec!["http://primary:8080", "http://replica:8080"], remote_urls("http://primary:8080, http://replica:8080,"));
+    }
+
+    #[test]
+    fn timestamp() {
+        assert_eq!("1970-01-01 00:00:00 UTC", format_timestamp(0));
+        assert_eq!("2023-02-14 09:30:05 UTC", format_timestamp(1_676_367_005_123_456_789));
+    }
+}
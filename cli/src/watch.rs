@@ -0,0 +1,241 @@
+use std::{collections::VecDeque, time::Duration};
+use anyhow::{Result, Context, anyhow};
+use reqwest::StatusCode;
+use senvy_common::{types::{ChangeEvent, ChangeKind, ProjectEntry}, sse::{Event, EventParser}};
+use serde_json::from_str;
+use tokio::{process::{Child, Command}, time::{sleep, timeout}};
+use url::Url;
+use crate::{
+    command_handlers::{fetch_entry, fetch_entry_since},
+    config::{Config, write_config},
+    utils::{write_env, response_error, send_failover, first_remote}
+};
+
+/// server sends a keep-alive every 15s, stream is considered dead after missing a few of them
+const IDLE_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// open change stream of a single project
+struct EventSource {
+    res: reqwest::Response,
+    parser: EventParser,
+    pending: VecDeque<Event>,
+}
+
+impl EventSource {
+    async fn connect(client: &reqwest::Client, remote_url: &str, name: &str) -> Result<Self> {
+        let mut endpoint = Url::parse(first_remote(remote_url))
+            .context("parsing remote url")?;
+        endpoint.path_segments_mut()
+            .map_err(|_| anyhow!("remote url can't have a path"))?
+            .clear()
+            .extend(["v1", "events"]);
+        endpoint.query_pairs_mut()
+            .append_pair("project", name);
+
+        let req = client.get(endpoint)
+            .header("Accept", "text/event-stream");
+        let res = send_failover(client, req, remote_url)
+            .await
+            .context("connecting to the change stream")?;
+        if res.status() != StatusCode::OK {
+            let status = res.status();
+            let body = res.text().await.unwrap_or_default();
+            return Err(response_error(status, &body))
+                .context("connecting to the change stream");
+        }
+
+        Ok(EventSource {
+            res,
+            parser: EventParser::default(),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// None means that the server closed the stream
+    async fn next(&mut self) -> Result<Option<Event>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+            let chunk = timeout(IDLE_TIMEOUT, self.res.chunk())
+                .await
+                .map_err(|_| anyhow!("no data from the server for {}s", IDLE_TIMEOUT.as_secs()))?
+                .context("reading the change stream")?;
+            match chunk {
+                Some(chunk) => self.pending.extend(self.parser.push(&chunk)),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// keeps the local var file in sync with the entry on the server
+pub struct Watcher {
+    pub client: reqwest::Client,
+    /// client without a total timeout, used only for the change stream
+    pub stream_client: reqwest::Client,
+    pub conf: Option<Config>,
+    pub name: String,
+    pub remote_url: String,
+    pub interval: Duration,
+    pub hook: Option<String>,
+    /// command restarted with the vars in its environment after every change
+    pub command: Vec<String>,
+    child: Option<Child>,
+    last_version: u128,
+}
+
+impl Watcher {
+    pub fn new(client: reqwest::Client, stream_client: reqwest::Client, conf: Option<Config>, name: String, remote_url: String) -> Self {
+        // version is only known if the local config belongs to the watched project
+        let last_version = conf.as_ref()
+            .filter(|c| c.name == name)
+            .map(|c| c.last_version)
+            .unwrap_or(0);
+        Watcher {
+            client,
+            stream_client,
+            conf,
+            name,
+            remote_url,
+            interval: Duration::from_secs(30),
+            hook: None,
+            command: Vec::new(),
+            child: None,
+            last_version,
+        }
+    }
+
+    /// runs until interrupted, errors while watching are printed and retried
+    pub async fn run(&mut self) -> Result<()> {
+        // first sync has to succeed, there is nothing to watch otherwise
+        let entry = fetch_entry(&self.client, &self.remote_url, &self.name).await?;
+        self.apply(entry, true).await?;
+
+        loop {
+            let source = EventSource::connect(&self.stream_client, &self.remote_url, &self.name).await;
+            let mut source = match source {
+                Ok(source) => source,
+                Err(err) => {
+                    println!("Change stream unavailable, checking again in {}s: {}", self.interval.as_secs(), err.root_cause());
+                    sleep(self.interval).await;
+                    self.sync().await;
+                    continue;
+                },
+            };
+            println!("Watching \"{}\" for changes", self.name);
+
+            // changes made while not connected
+            self.sync().await;
+            loop {
+                match source.next().await {
+                    Ok(Some(event)) => self.handle(event).await,
+                    Ok(None) => {
+                        println!("Change stream closed by the server, reconnecting");
+                        sleep(Duration::from_secs(1)).await;
+                        break;
+                    },
+                    Err(err) => {
+                        println!("Change stream interrupted, reconnecting: {}", err.root_cause());
+                        sleep(Duration::from_secs(1)).await;
+                        break;
+                    },
+                }
+            }
+        }
+    }
+
+    async fn handle(&mut self, event: Event) {
+        match event.name.as_str() {
+            "change" => {
+                let change: Result<ChangeEvent, _> = from_str(&event.data);
+                match change {
+                    Ok(change) if change.kind == ChangeKind::Deleted =>
+                        println!("Project entry \"{}\" was deleted on the server, keeping the local file", self.name),
+                    Ok(change) if change.timestamp <= self.last_version => {},
+                    Ok(change) => {
+                        if !change.vars.is_empty() {
+                            println!("Changed vars: {}", change.vars.join(", "));
+                        }
+                        self.sync().await;
+                    },
+                    Err(err) => println!("Unexpected event from the server: {}", err),
+                }
+            },
+            // some changes were missed, only a full read is reliable
+            "lagged" => self.sync().await,
+            _ => {},
+        }
+    }
+
+    /// pulls the entry and applies it if it is newer, errors are only printed
+    async fn sync(&mut self) {
+        let entry = fetch_entry_since(&self.client, &self.remote_url, &self.name, self.last_version).await;
+        let res = match entry {
+            Ok(Some(entry)) => self.apply(entry, false).await,
+            Ok(None) => Ok(()),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = res {
+            println!("Error syncing vars: {}: {}", err, err.root_cause());
+        }
+    }
+
+    /// writes the entry if it is newer than the local version and reacts to the change
+    /// the child is always started on the first run even when there is no change
+    async fn apply(&mut self, entry: ProjectEntry, first: bool) -> Result<()> {
+        let changed = entry.timestamp > self.last_version;
+        if changed {
+            self.last_version = entry.timestamp;
+            if let Some(conf) = self.conf.as_mut().filter(|c| c.name == self.name) {
+                conf.last_version = entry.timestamp;
+                conf.path = entry.path.clone();
+                write_config(conf)?;
+            }
+            write_env(entry.clone())?;
+            println!("Synced vars to {}", entry.path);
+
+            if let Some(hook) = self.hook.as_ref() {
+                run_hook(hook).await;
+            }
+        }
+        if changed || first {
+            self.restart(&entry).await?;
+        }
+        Ok(())
+    }
+
+    async fn restart(&mut self, entry: &ProjectEntry) -> Result<()> {
+        if self.command.is_empty() {
+            return Ok(());
+        }
+        if let Some(mut child) = self.child.take() {
+            println!("Restarting {}", self.command[0]);
+            // error means that the child already exited
+            _ = child.kill().await;
+        }
+
+        let child = Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .envs(entry.vars.iter().map(|v| (&v.name, &v.value)))
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("starting {}", self.command[0]))?;
+        self.child = Some(child);
+        Ok(())
+    }
+}
+
+/// runs the hook in the shell, failures are only reported
+async fn run_hook(hook: &str) {
+    let status = if cfg!(windows) {
+        Command::new("cmd").args(["/C", hook]).status().await
+    } else {
+        Command::new("sh").args(["-c", hook]).status().await
+    };
+    match status {
+        Ok(status) if !status.success() => println!("Hook exited with {}", status),
+        Err(err) => println!("Error running hook: {}", err),
+        _ => {},
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+use serde_derive::{Serialize, Deserialize};
+use crate::schema::Violation;
+
+/// error returned by the server as the json body of every unsuccessful response
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    /// human readable description of the error
+    pub message: String,
+    /// extra data depending on the code, list of violations for schema errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+/// machine readable kind of the error
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidName,
+    NotFound,
+    AlreadyExists,
+    SchemaViolation,
+    MalformedJson,
+    Internal,
+    /// server can't handle requests right now
+    Unavailable,
+    /// batch operation was not applied because another operation of the batch failed
+    Aborted,
+    /// request body is over the limit of the server
+    PayloadTooLarge,
+    /// write sent to a replica, it only takes changes from its primary
+    ReadOnly,
+    /// client sent more requests than the server allows, retried after a while
+    RateLimited,
+    /// admin endpoint called without the admin token or while admin endpoints are disabled
+    Forbidden,
+    /// code added by a newer server
+    #[serde(other)]
+    Unknown,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: &str) -> Self {
+        ApiError {
+            code,
+            message: message.to_string(),
+            details: None,
+        }
+    }
+
+    /// error for vars that do not follow the schema of the project
+    pub fn schema_violation(violations: Vec<Violation>) -> Self {
+        ApiError {
+            code: ErrorCode::SchemaViolation,
+            message: "vars do not follow the schema".to_string(),
+            details: serde_json::to_value(violations).ok(),
+        }
+    }
+
+    /// violations in the details, empty if there are none
+    pub fn violations(&self) -> Vec<Violation> {
+        self.details.clone()
+            .and_then(|d| serde_json::from_value(d).ok())
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        for violation in self.violations() {
+            write!(f, "\n    {}: {}", violation.var, violation.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let err = ApiError::schema_violation(vec![Violation {
+            var: "PORT".to_string(),
+            message: "value is not a valid port".to_string(),
+        }]);
+        let res = serde_json::to_string(&err).unwrap();
+        assert_eq!(r#"{"code":"schema_violation","message":"vars do not follow the schema","details":[{"message":"value is not a valid port","var":"PORT"}]}"#, res);
+        assert_eq!("vars do not follow the schema\n    PORT: value is not a valid port", err.to_string());
+
+        let err: ApiError = serde_json::from_str(r#"{"code":"something_new","message":"test"}"#).unwrap();
+        assert_eq!(ErrorCode::Unknown, err.code);
+    }
+}
@@ -1 +1,4 @@
 pub mod types;
+pub mod schema;
+pub mod error;
+pub mod sse;
@@ -0,0 +1,203 @@
+use serde_derive::{Serialize, Deserialize};
+use regex::Regex;
+use url::Url;
+use crate::types::Var;
+
+/// rules vars of a project have to follow
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Schema {
+    pub vars: Vec<SchemaVar>,
+}
+
+/// rules for a single var
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SchemaVar {
+    pub name: String,
+    /// required vars without a default have to be present
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default, rename = "type")]
+    pub kind: VarType,
+    /// allowed values when the type is enum
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<String>>,
+    /// regex the whole value has to match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// type the value of a var has to parse as
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VarType {
+    #[default]
+    String,
+    Int,
+    Url,
+    Bool,
+    Port,
+    Enum,
+}
+
+/// single broken rule
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// name of the var breaking the rule
+    pub var: String,
+    pub message: String,
+}
+
+impl Schema {
+    /// checks the vars against the schema, empty result means that the vars are valid
+    /// broken rules of the schema itself are reported as violations as well
+    pub fn validate(&self, vars: &[Var]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for rule in self.vars.iter() {
+            let violation = |message: String| Violation {
+                var: rule.name.clone(),
+                message,
+            };
+
+            let pattern = match rule.pattern.as_ref().map(|p| Regex::new(&format!("^(?:{})$", p))) {
+                Some(Err(err)) => {
+                    violations.push(violation(format!("schema has an invalid pattern: {}", err)));
+                    continue;
+                },
+                Some(Ok(pattern)) => Some(pattern),
+                None => None,
+            };
+            if rule.kind == VarType::Enum && rule.values.as_ref().map(|v| v.is_empty()).unwrap_or(true) {
+                violations.push(violation("schema has an enum type without values".to_string()));
+                continue;
+            }
+            // default is used in place of a missing var so it has to follow the rule as well
+            if let Some(default) = &rule.default {
+                let invalid = match rule.kind.check(default, rule.values.as_deref()) {
+                    Err(message) => Some(message),
+                    Ok(()) if pattern.as_ref().map(|p| !p.is_match(default)).unwrap_or(false) =>
+                        Some(format!("value does not match pattern \"{}\"", rule.pattern.as_ref().unwrap())),
+                    Ok(()) => None,
+                };
+                if let Some(message) = invalid {
+                    violations.push(violation(format!("schema has an invalid default, {}", message)));
+                    continue;
+                }
+            }
+
+            let var = vars.iter().find(|v| v.name == rule.name);
+            if var.is_none() {
+                if rule.required && rule.default.is_none() {
+                    violations.push(violation("required var is missing".to_string()));
+                }
+                continue;
+            }
+            let value = &var.unwrap().value;
+
+            if let Err(message) = rule.kind.check(value, rule.values.as_deref()) {
+                violations.push(violation(message));
+            }
+            if let Some(pattern) = pattern {
+                if !pattern.is_match(value) {
+                    violations.push(violation(format!("value does not match pattern \"{}\"", rule.pattern.as_ref().unwrap())));
+                }
+            }
+        }
+        violations
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SchemaVar> {
+        self.vars.iter().find(|v| v.name == name)
+    }
+}
+
+impl VarType {
+    /// err holds the reason the value is not of this type
+    fn check(&self, value: &str, values: Option<&[String]>) -> Result<(), String> {
+        let valid = match self {
+            VarType::String => true,
+            VarType::Int => value.parse::<i64>().is_ok(),
+            VarType::Url => Url::parse(value).is_ok(),
+            VarType::Bool => matches!(value.to_lowercase().as_str(),
+                "true" | "false" | "1" | "0" | "yes" | "no"),
+            VarType::Port => matches!(value.parse::<u16>(), Ok(p) if p != 0),
+            VarType::Enum => values.unwrap_or_default().iter().any(|v| v == value),
+        };
+        if valid {
+            return Ok(());
+        }
+        match self {
+            VarType::Enum => Err(format!("value is not one of: {}", values.unwrap_or_default().join(", "))),
+            _ => Err(format!("value is not a valid {}", self.name())),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VarType::String => "string",
+            VarType::Int => "int",
+            VarType::Url => "url",
+            VarType::Bool => "bool",
+            VarType::Port => "port",
+            VarType::Enum => "enum",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str, value: &str) -> Var {
+        Var::new(name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn validate() {
+        let schema = Schema {
+            vars: vec![
+                SchemaVar { name: "PORT".to_string(), required: true, kind: VarType::Port, ..Default::default() },
+                SchemaVar { name: "HOST".to_string(), required: true, default: Some("localhost".to_string()), ..Default::default() },
+                SchemaVar { name: "URL".to_string(), required: true, kind: VarType::Url, ..Default::default() },
+                SchemaVar {
+                    name: "MODE".to_string(),
+                    kind: VarType::Enum,
+                    values: Some(vec!["dev".to_string(), "prod".to_string()]),
+                    ..Default::default()
+                },
+                SchemaVar { name: "ID".to_string(), pattern: Some("[a-z]+".to_string()), ..Default::default() },
+            ],
+        };
+
+        let vars = vec![var("PORT", "8080"), var("URL", "https://senvy.test"), var("MODE", "dev"), var("ID", "abc")];
+        assert_eq!(Vec::<Violation>::new(), schema.validate(&vars));
+
+        let vars = vec![var("PORT", "99999"), var("MODE", "test"), var("ID", "abc1")];
+        let violations: Vec<String> = schema.validate(&vars)
+            .into_iter()
+            .map(|v| v.var)
+            .collect();
+        assert_eq!(vec!["PORT", "URL", "MODE", "ID"], violations);
+    }
+
+    #[test]
+    fn invalid_schema() {
+        let schema = Schema {
+            vars: vec![
+                SchemaVar { name: "ID".to_string(), pattern: Some("[a-z".to_string()), ..Default::default() },
+                SchemaVar { name: "MODE".to_string(), kind: VarType::Enum, ..Default::default() },
+                SchemaVar { name: "PORT".to_string(), kind: VarType::Port, default: Some("http".to_string()), ..Default::default() },
+                SchemaVar {
+                    name: "HOST".to_string(),
+                    pattern: Some("[a-z]+".to_string()),
+                    default: Some("127.0.0.1".to_string()),
+                    ..Default::default()
+                },
+            ],
+        };
+        assert_eq!(4, schema.validate(&[]).len());
+    }
+}
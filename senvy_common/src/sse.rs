@@ -0,0 +1,67 @@
+/// single server-sent event
+#[derive(Debug, PartialEq)]
+pub struct Event {
+    pub name: String,
+    pub data: String,
+}
+
+/// incremental parser of a server-sent events stream
+#[derive(Default)]
+pub struct EventParser {
+    buff: Vec<u8>,
+}
+
+impl EventParser {
+    /// adds a chunk of the stream, returns events completed by it
+    /// blocks without data, like keep-alive comments, are skipped
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Event> {
+        self.buff.extend(chunk.iter().filter(|b| **b != b'\r'));
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buff.windows(2).position(|w| w == b"\n\n") {
+            let block: Vec<u8> = self.buff.drain(..end + 2).collect();
+            let block = String::from_utf8_lossy(&block);
+
+            let mut name = "message".to_string();
+            let mut data: Option<String> = None;
+            for line in block.lines() {
+                if let Some(value) = line.strip_prefix("event:") {
+                    name = value.trim_start().to_string();
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    let value = value.strip_prefix(' ').unwrap_or(value);
+                    match data.as_mut() {
+                        Some(data) => {
+                            data.push('\n');
+                            data.push_str(value);
+                        },
+                        None => data = Some(value.to_string()),
+                    }
+                }
+            }
+            if let Some(data) = data {
+                events.push(Event { name, data });
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_events() {
+        let mut parser = EventParser::default();
+        assert_eq!(Vec::<Event>::new(), parser.push(b": keep-alive\n\nevent: change\r\ndata: {\"a\":"));
+
+        let events = parser.push(b"1}\n\nevent: lagged\ndata: 3\n\ndata: x");
+        assert_eq!(vec![
+            Event { name: "change".to_string(), data: "{\"a\":1}".to_string() },
+            Event { name: "lagged".to_string(), data: "3".to_string() },
+        ], events);
+
+        let events = parser.push(b"\ndata: y\n\n");
+        assert_eq!(vec![Event { name: "message".to_string(), data: "x\ny".to_string() }], events);
+    }
+}
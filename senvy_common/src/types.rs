@@ -1,12 +1,185 @@
+use std::collections::BTreeMap;
 use serde_derive::{Serialize, Deserialize};
+use crate::{schema::Schema, error::{ApiError, ErrorCode}};
 
 /// data about a project when creating a new one or updating already existing
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Project{
-    /// project name
+    /// project name, can be left out when the name is a part of the url
+    #[serde(default)]
     pub name: String,
     pub path: String,
     pub vars: Vec<Var>,
+    /// who is making the change, recorded as the author of changed vars
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// schema to store with the entry, when updating None keeps the stored one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Schema>,
+}
+
+/// partial change of an existing project, provided fields replace the stored ones
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ProjectPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vars: Option<Vec<Var>>,
+    /// vars added or replaced by name, applied after unset
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub set: Vec<Var>,
+    /// names of vars to remove, names that don't exist are ignored
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unset: Vec<String>,
+    /// who is making the change, recorded as the author of changed vars
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+/// version of the entry after a successful change
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EntryVersion {
+    pub timestamp: u128,
+}
+
+/// name of the project a project is renamed or copied to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProjectTarget {
+    pub name: String,
+}
+
+/// operations on several projects executed by the server as a single unit
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Batch {
+    pub operations: Vec<BatchOperation>,
+    /// all operations are applied or none of them
+    #[serde(default)]
+    pub atomic: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BatchOperation {
+    Read { name: String },
+    /// creates the project only if it doesn't exist
+    Create(Project),
+    /// replaces the project only if it exists
+    Update(Project),
+    Delete { name: String },
+}
+
+impl BatchOperation {
+    pub fn project_name(&self) -> &str {
+        match self {
+            BatchOperation::Read { name } | BatchOperation::Delete { name } => name,
+            BatchOperation::Create(project) | BatchOperation::Update(project) => &project.name,
+        }
+    }
+
+    pub fn is_write(&self) -> bool {
+        !matches!(self, BatchOperation::Read { .. })
+    }
+}
+
+/// results of the operations in the same order as they were sent
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BatchResponse {
+    /// atomic batch failed and every change it made was undone
+    pub rolled_back: bool,
+    pub results: Vec<OperationResult>,
+}
+
+/// outcome of a single operation, status is the one the single request would get
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OperationResult {
+    pub status: u16,
+    /// version after a successful create or update
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u128>,
+    /// entry of a successful read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry: Option<ProjectEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ApiError>,
+}
+
+impl OperationResult {
+    pub fn new(status: u16) -> Self {
+        OperationResult {
+            status,
+            timestamp: None,
+            entry: None,
+            error: None,
+        }
+    }
+
+    pub fn failed(status: u16, error: ApiError) -> Self {
+        OperationResult {
+            error: Some(error),
+            ..OperationResult::new(status)
+        }
+    }
+
+    /// operation of an atomic batch that was undone or never executed
+    pub fn aborted() -> Self {
+        OperationResult::failed(424, ApiError::new(ErrorCode::Aborted, "not applied because another operation failed"))
+    }
+}
+
+/// snapshot of every entry on the server, no change is half applied in it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Archive {
+    /// time the snapshot was taken
+    pub timestamp: u128,
+    /// project name -> entry
+    pub entries: BTreeMap<String, ProjectEntry>,
+}
+
+/// what restoring an archive does with entries that already exist
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// nothing is restored if any of the entries exists
+    #[default]
+    Fail,
+    /// existing entries are kept
+    Skip,
+    /// existing entries are replaced
+    Overwrite,
+}
+
+/// names of the projects restored from an archive
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct RestoreResponse {
+    pub restored: Vec<String>,
+    /// entries that already existed and were kept
+    pub skipped: Vec<String>,
+}
+
+/// change of a project entry streamed to watching clients
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChangeEvent {
+    pub project: String,
+    pub kind: ChangeKind,
+    /// timestamp of the new version, time of removal for deleted entries
+    pub timestamp: u128,
+    /// names of vars that were added, removed or changed
+    pub vars: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// setting or removing the schema of an existing project
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProjectSchema {
+    pub name: String,
+    pub schema: Option<Schema>,
 }
 
 /// name of the project is based on the file name
@@ -17,11 +190,110 @@ pub struct ProjectEntry {
     pub timestamp: u128,
     pub path: String,
     pub vars: Vec<Var>,
+    /// rules vars have to follow, entries stored without one have no rules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Schema>,
+}
+
+/// entity tag of the entry version, sent by the server with every read
+pub fn etag(timestamp: u128) -> String {
+    format!("\"{}\"", timestamp)
 }
 
 /// name value pair - env var
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// metadata is optional so that entries stored without it are still valid
+/// metadata left out when updating is kept from the stored var with the same name
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct Var {
     pub name: String,
     pub value: String,
+    /// what the var is used for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// secret vars are masked in cli output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<bool>,
+    /// person or team responsible for the var
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// timestamp of the last change of the value, set by the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u128>,
+    /// author of the last change of the value, set by the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_by: Option<String>,
+}
+
+impl Var {
+    /// var without any metadata
+    pub fn new(name: String, value: String) -> Self {
+        Var {
+            name,
+            value,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_secret(&self) -> bool {
+        self.secret.unwrap_or(false)
+    }
+
+    /// takes metadata from the old var for every field that is not set
+    /// empty description, owner or tags clear the field instead
+    pub fn inherit_metadata(&mut self, old: Option<&Var>) {
+        if let Some(old) = old {
+            if self.description.is_none() {
+                self.description = old.description.clone();
+            }
+            if self.secret.is_none() {
+                self.secret = old.secret;
+            }
+            if self.owner.is_none() {
+                self.owner = old.owner.clone();
+            }
+            if self.tags.is_none() {
+                self.tags = old.tags.clone();
+            }
+        }
+        if self.description.as_deref() == Some("") {
+            self.description = None;
+        }
+        if self.owner.as_deref() == Some("") {
+            self.owner = None;
+        }
+        if self.tags.as_ref().map(|t| t.is_empty()).unwrap_or(false) {
+            self.tags = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inheriting_metadata() {
+        let old = Var {
+            description: Some("port of the api".to_string()),
+            owner: Some("backend".to_string()),
+            tags: Some(vec!["api".to_string()]),
+            ..Var::new("PORT".to_string(), "8080".to_string())
+        };
+
+        let mut var = Var::new("PORT".to_string(), "9090".to_string());
+        var.inherit_metadata(Some(&old));
+        assert_eq!((old.description.clone(), old.owner.clone(), old.tags.clone()), (var.description, var.owner, var.tags));
+
+        // empty values clear the metadata
+        let mut var = Var {
+            description: Some(String::new()),
+            owner: Some(String::new()),
+            tags: Some(Vec::new()),
+            ..Var::new("PORT".to_string(), "9090".to_string())
+        };
+        var.inherit_metadata(Some(&old));
+        assert_eq!(Var::new("PORT".to_string(), "9090".to_string()), var);
+    }
 }
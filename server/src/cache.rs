@@ -0,0 +1,129 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex}
+};
+use senvy_common::types::ProjectEntry;
+use crate::metrics::Metrics;
+
+/// least recently used entries, oldest are evicted when the cache is full
+struct Lru {
+    /// project name -> entry and the tick of its last use
+    entries: HashMap<String, (ProjectEntry, u64)>,
+    /// tick of the last use -> project name, first is the oldest
+    uses: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+/// entries read by the worker kept in memory, invalidated by every task writing to them
+pub struct EntryCache {
+    /// max number of entries, 0 turns the cache off
+    capacity: usize,
+    lru: Mutex<Lru>,
+    metrics: Arc<Metrics>,
+}
+
+impl EntryCache {
+    pub fn new(capacity: usize, metrics: Arc<Metrics>) -> Self {
+        EntryCache {
+            capacity,
+            lru: Mutex::new(Lru {
+                entries: HashMap::new(),
+                uses: BTreeMap::new(),
+                tick: 0,
+            }),
+            metrics,
+        }
+    }
+
+    /// cached entry, marked as the most recently used
+    pub fn get(&self, project_name: &str) -> Option<ProjectEntry> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let mut lru = self.lru.lock().ok()?;
+        lru.tick += 1;
+        let tick = lru.tick;
+
+        let Lru { entries, uses, .. } = &mut *lru;
+        let res = entries.get_mut(project_name).map(|(entry, used)| {
+            uses.remove(used);
+            uses.insert(tick, project_name.to_string());
+            *used = tick;
+            entry.clone()
+        });
+        self.metrics.observe_cache(res.is_some());
+        res
+    }
+
+    /// caches the entry, evicting the least recently used one if full
+    pub fn insert(&self, project_name: &str, entry: ProjectEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        let Ok(mut lru) = self.lru.lock() else {
+            return;
+        };
+        lru.tick += 1;
+        let tick = lru.tick;
+
+        let Lru { entries, uses, .. } = &mut *lru;
+        if let Some((_, used)) = entries.insert(project_name.to_string(), (entry, tick)) {
+            uses.remove(&used);
+        }
+        uses.insert(tick, project_name.to_string());
+        while entries.len() > self.capacity {
+            let Some((_, oldest)) = uses.pop_first() else {
+                break;
+            };
+            entries.remove(&oldest);
+        }
+        self.metrics.set_cache_entries(entries.len());
+    }
+
+    pub fn invalidate(&self, project_name: &str) {
+        let Ok(mut lru) = self.lru.lock() else {
+            return;
+        };
+        let Lru { entries, uses, .. } = &mut *lru;
+        if let Some((_, used)) = entries.remove(project_name) {
+            uses.remove(&used);
+        }
+        self.metrics.set_cache_entries(entries.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: u128) -> ProjectEntry {
+        ProjectEntry {
+            timestamp,
+            vars: Vec::new(),
+            path: "./.env".to_string(),
+            schema: None,
+        }
+    }
+
+    #[test]
+    fn eviction() {
+        let metrics = Arc::new(Metrics::new());
+        let cache = EntryCache::new(2, metrics.clone());
+        cache.insert("a", entry(1));
+        cache.insert("b", entry(2));
+
+        // a is used so b is the oldest when c comes in
+        assert_eq!(Some(entry(1)), cache.get("a"));
+        cache.insert("c", entry(3));
+        assert_eq!(None, cache.get("b"));
+        assert_eq!(Some(entry(3)), cache.get("c"));
+
+        cache.invalidate("a");
+        assert_eq!(None, cache.get("a"));
+
+        let res = metrics.render(0);
+        assert!(res.contains("senvy_cache_hits_total 2\n"));
+        assert!(res.contains("senvy_cache_misses_total 2\n"));
+        assert!(res.contains("senvy_cache_entries 1\n"));
+    }
+}
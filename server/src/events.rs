@@ -0,0 +1,127 @@
+use std::sync::Arc;
+use tokio::sync::{
+    broadcast::{self, Sender, Receiver},
+    watch
+};
+use senvy_common::types::{ChangeEvent, ChangeKind, ProjectEntry};
+
+/// number of events kept for slow subscribers before they start lagging
+const CAPACITY: usize = 256;
+
+/// fan out of entry changes made by the worker to every watching client
+#[derive(Clone)]
+pub struct Events {
+    sender: Sender<ChangeEvent>,
+    /// set to true when the server is shutting down so streams can end
+    closed: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Events {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        let (closed, _) = watch::channel(false);
+        Events {
+            sender,
+            closed: Arc::new(closed),
+        }
+    }
+
+    /// ends every open stream, open streams would otherwise keep the server from shutting down
+    pub fn close(&self) {
+        self.closed.send_replace(true);
+    }
+
+    pub fn closed(&self) -> watch::Receiver<bool> {
+        self.closed.subscribe()
+    }
+
+    pub fn subscribe(&self) -> Receiver<ChangeEvent> {
+        self.sender.subscribe()
+    }
+
+    /// whether there is anyone to send events to, used to skip computing them
+    pub fn listening(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    /// sends the event for the change between two versions of the entry
+    /// nothing is sent if neither version exists
+    pub fn publish(&self, project: &str, timestamp: u128, old: Option<&ProjectEntry>, new: Option<&ProjectEntry>) {
+        let (kind, timestamp) = match (old, new) {
+            (None, None) => return,
+            (None, Some(new)) => (ChangeKind::Created, new.timestamp),
+            (Some(_), Some(new)) => (ChangeKind::Updated, new.timestamp),
+            (Some(_), None) => (ChangeKind::Deleted, timestamp),
+        };
+        let event = ChangeEvent {
+            project: project.to_string(),
+            kind,
+            timestamp,
+            vars: changed_vars(old, new),
+        };
+        // error only means that everyone unsubscribed in the meantime
+        _ = self.sender.send(event);
+    }
+}
+
+/// names of vars that differ between the versions, in order of appearance
+fn changed_vars(old: Option<&ProjectEntry>, new: Option<&ProjectEntry>) -> Vec<String> {
+    let old_vars = old.map(|e| e.vars.as_slice()).unwrap_or_default();
+    let new_vars = new.map(|e| e.vars.as_slice()).unwrap_or_default();
+
+    let mut names: Vec<String> = new_vars.iter()
+        .filter(|v| !old_vars.contains(v))
+        .map(|v| v.name.clone())
+        .collect();
+    for var in old_vars {
+        if !new_vars.iter().any(|v| v.name == var.name) {
+            names.push(var.name.clone());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use senvy_common::types::Var;
+    use super::*;
+
+    fn entry(timestamp: u128, vars: &[(&str, &str)]) -> ProjectEntry {
+        ProjectEntry {
+            timestamp,
+            path: "./.env".to_string(),
+            schema: None,
+            vars: vars.iter()
+                .map(|(n, v)| Var::new(n.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn publish() {
+        let events = Events::new();
+        let mut rx = events.subscribe();
+        assert!(events.listening());
+
+        let old = entry(1, &[("PORT", "8080"), ("HOST", "localhost"), ("MODE", "dev")]);
+        let new = entry(2, &[("PORT", "9090"), ("HOST", "localhost"), ("ID", "1")]);
+        events.publish("test", 2, Some(&old), Some(&new));
+        let event = rx.try_recv().unwrap();
+        assert_eq!(ChangeKind::Updated, event.kind);
+        assert_eq!(2, event.timestamp);
+        assert_eq!(vec!["PORT", "ID", "MODE"], event.vars);
+
+        events.publish("test", 3, Some(&new), None);
+        let event = rx.try_recv().unwrap();
+        assert_eq!((ChangeKind::Deleted, 3, 3), (event.kind, event.timestamp, event.vars.len()));
+
+        events.publish("test", 4, None, None);
+        assert!(rx.try_recv().is_err());
+    }
+}
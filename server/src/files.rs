@@ -1,12 +1,19 @@
 use std::{
-    fs::{OpenOptions, remove_file},
+    collections::BTreeMap,
+    sync::atomic::{AtomicU64, Ordering},
+    fs::{OpenOptions, File, remove_file, read_dir, metadata},
     io::{Write, Read}
 };
 use anyhow::Result;
 use serde_json::{
-    to_vec, from_str
+    from_str
 };
-use senvy_common::types::{Project, ProjectEntry};
+use senvy_common::{
+    types::{Archive, Batch, BatchOperation, BatchResponse, ConflictPolicy, OperationResult, Project, ProjectEntry, ProjectPatch, ProjectSchema, EntryVersion, RestoreResponse, Var},
+    schema::{Schema, Violation},
+    error::{ApiError, ErrorCode}
+};
+use crate::journal::journal;
 
 /// prefix file names with "data/"
 macro_rules! path_prefix {
@@ -17,37 +24,149 @@ macro_rules! path_prefix {
     };
 }
 
-/// initializing a new project
-/// err indicates fs or json error
-/// false means that file already exists
-pub async fn create(timestamp: u128, project_info: Project) -> Result<bool> {
-    // opening file with create_new option will fail if it already exists
-    let path = path_prefix!(project_info.name);
-    let file = OpenOptions::new()
-        .create_new(true)
+/// result of writing to a project entry
+#[derive(Debug, PartialEq)]
+pub enum WriteResult {
+    Done,
+    /// creating an entry that already exists
+    Exists,
+    /// changing an entry that doesn't exist
+    NotFound,
+    /// vars don't follow the schema of the entry
+    Invalid(Vec<Violation>),
+}
+
+/// result of restoring an archive, nothing is restored unless it is done
+#[derive(Debug, PartialEq)]
+pub enum RestoreResult {
+    Done(RestoreResponse),
+    /// entries that exist while the policy is fail
+    Conflicts(Vec<String>),
+    /// entry whose vars don't follow its schema
+    Invalid(String, Vec<Violation>),
+}
+
+/// number of the next readiness check, every check uses its own file
+static READY_CHECKS: AtomicU64 = AtomicU64::new(0);
+
+/// checks that entries can be written by creating and removing a reserved file
+/// err indicates fs error
+pub fn writable() -> Result<()> {
+    let check = READY_CHECKS.fetch_add(1, Ordering::Relaxed);
+    let path = path_prefix!(format!(".ready-{}-{}", std::process::id(), check));
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(true)
         .write(true)
-        .open(path);
+        .open(&path)?;
+    file.write_all(b"ready")?;
+    remove_file(path)?;
+    Ok(())
+}
 
-    if file.is_err() {
-        let err = file.err().unwrap();
-        match err.kind() {
-            std::io::ErrorKind::AlreadyExists => return Ok(false),
-            _ => return Err(err.into()),
+/// flushes every entry file and the data directory to the disk, reserved files are skipped
+/// err indicates fs error, the rest of the files are still flushed
+pub fn sync() -> Result<()> {
+    let mut res = Ok(());
+    for entry in read_dir(path_prefix!(""))? {
+        // reserved files like the journal and readiness checks are not entries
+        let entry = match entry {
+            Ok(entry) if entry.file_name().to_string_lossy().starts_with('.') => continue,
+            entry => entry,
+        };
+        let synced = entry
+            .and_then(|e| File::open(e.path()))
+            .and_then(|f| f.sync_all());
+        match synced {
+            // entry removed after the directory was listed has nothing left to flush
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {},
+            Err(err) => res = Err(err.into()),
+            Ok(()) => {},
         }
     }
-    let mut file = file.unwrap();
+    // directory holds the names of created, renamed and deleted entries
+    File::open(path_prefix!(""))?.sync_all()?;
+    res
+}
+
+/// checks vars against the schema if there is one
+fn validate(schema: &Option<Schema>, vars: &[Var]) -> Option<Vec<Violation>> {
+    let violations = schema.as_ref()?.validate(vars);
+    if violations.is_empty() {
+        return None;
+    }
+    Some(violations)
+}
+
+/// overwriting an existing entry file
+/// false means that file doesn't exist
+fn overwrite(project_name: &str, data: &ProjectEntry) -> Result<bool> {
+    if !exists(project_name)? {
+        return Ok(false);
+    }
+    store(project_name, Some(data))?;
+    Ok(true)
+}
+
+/// checks if the entry file exists
+/// err indicates fs error
+fn exists(project_name: &str) -> Result<bool> {
+    match metadata(path_prefix!(project_name)) {
+        Ok(_) => Ok(true),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// changing the entry file through the journal, None removes the entry
+/// tasks on the same project don't run at the same time so checking before storing is safe
+/// err indicates fs or json error
+fn store(project_name: &str, data: Option<&ProjectEntry>) -> Result<()> {
+    journal().write(project_name, data)
+}
+
+/// stamps vars whose value is new or changed with the timestamp and the author
+/// unchanged vars keep their stamp, metadata not provided is kept from the old var
+fn merge_vars(timestamp: u128, author: &Option<String>, old: &[Var], new: Vec<Var>) -> Vec<Var> {
+    new.into_iter()
+        .map(|mut var| {
+            let old_var = old.iter().find(|o| o.name == var.name);
+            match old_var {
+                Some(old_var) if old_var.value == var.value => {
+                    var.modified = old_var.modified;
+                    var.modified_by = old_var.modified_by.clone();
+                },
+                _ => {
+                    var.modified = Some(timestamp);
+                    var.modified_by = author.clone();
+                },
+            }
+            var.inherit_metadata(old_var);
+            var
+        })
+        .collect()
+}
+
+/// initializing a new project
+/// err indicates fs or json error
+/// vars are checked against the schema provided with the project
+pub async fn create(timestamp: u128, project_info: Project) -> Result<WriteResult> {
+    if let Some(violations) = validate(&project_info.schema, &project_info.vars) {
+        return Ok(WriteResult::Invalid(violations));
+    }
+
+    if exists(&project_info.name)? {
+        return Ok(WriteResult::Exists);
+    }
 
-    // serialize
     let data = ProjectEntry {
         timestamp,
-        vars: project_info.vars,
+        vars: merge_vars(timestamp, &project_info.author, &[], project_info.vars),
         path: project_info.path,
+        schema: project_info.schema,
     };
-    let data = to_vec(&data)?;
-
-    // write
-    file.write_all(&data)?;
-    Ok(true)
+    store(&project_info.name, Some(&data))?;
+    Ok(WriteResult::Done)
 }
 
 /// reading already existing project
@@ -73,54 +192,334 @@ pub async fn read(project_name: &str) -> Result<Option<ProjectEntry>> {
     Ok(Some(data))
 }
 
-/// updating already existing project
-/// err indicates fs or json error
-/// false means that file doesn't exist
-pub async fn update(timestamp: u128, project_info: Project) -> Result<bool> {
-    let path = path_prefix!(project_info.name);
+/// version of already existing project without parsing the vars
+/// err indicates fs error, json error should not happen
+/// None means that file doesn't exist
+pub async fn version(project_name: &str) -> Result<Option<u128>> {
+    let path = path_prefix!(project_name);
     let file = OpenOptions::new()
-        .truncate(true)
-        .write(true)
+        .read(true)
         .open(path);
-
     if file.is_err() {
         let err = file.err().unwrap();
         match err.kind() {
-            std::io::ErrorKind::NotFound => return Ok(false),
+            std::io::ErrorKind::NotFound => return Ok(None),
             _ => return Err(err.into()),
         }
     }
 
     let mut file = file.unwrap();
+    let mut buff = String::new();
+    file.read_to_string(&mut buff)?;
+    // every other field is skipped
+    let data: EntryVersion = from_str(&buff)?;
+    Ok(Some(data.timestamp))
+}
+
+/// updating already existing project
+/// err indicates fs or json error
+/// vars are checked against the provided schema or the stored one if not provided
+pub async fn update(timestamp: u128, project_info: Project) -> Result<WriteResult> {
+    // old entry is needed to tell which vars changed
+    let old = read(&project_info.name).await?;
+    if old.is_none() {
+        return Ok(WriteResult::NotFound);
+    }
+    let old = old.unwrap();
+
+    let schema = project_info.schema.or(old.schema);
+    if let Some(violations) = validate(&schema, &project_info.vars) {
+        return Ok(WriteResult::Invalid(violations));
+    }
+
     let data = ProjectEntry{
         timestamp,
-        vars: project_info.vars,
-        path: project_info.path
+        vars: merge_vars(timestamp, &project_info.author, &old.vars, project_info.vars),
+        path: project_info.path,
+        schema,
     };
-    let data = to_vec(&data)?;
-    file.write_all(&data)?;
-    Ok(true)
+    if !overwrite(&project_info.name, &data)? {
+        return Ok(WriteResult::NotFound);
+    }
+    Ok(WriteResult::Done)
+}
+
+/// creating a project or replacing already existing one
+/// err indicates fs or json error
+/// true next to the result means that the project was created
+pub async fn put(timestamp: u128, project_info: Project) -> Result<(WriteResult, bool)> {
+    let res = update(timestamp, project_info.clone()).await?;
+    if res != WriteResult::NotFound {
+        return Ok((res, false));
+    }
+    let res = create(timestamp, project_info).await?;
+    Ok((res, true))
+}
+
+/// changing only the provided fields of already existing project
+/// err indicates fs or json error
+pub async fn patch(timestamp: u128, project_name: String, project_patch: ProjectPatch) -> Result<WriteResult> {
+    let old = read(&project_name).await?;
+    if old.is_none() {
+        return Ok(WriteResult::NotFound);
+    }
+    let old = old.unwrap();
+
+    // operations on single vars are applied on top of the replaced or stored ones
+    let mut vars = project_patch.vars.unwrap_or(old.vars);
+    vars.retain(|v| !project_patch.unset.contains(&v.name));
+    for var in project_patch.set {
+        match vars.iter_mut().find(|v| v.name == var.name) {
+            Some(old_var) => *old_var = var,
+            None => vars.push(var),
+        }
+    }
+
+    let project_info = Project {
+        name: project_name,
+        path: project_patch.path.unwrap_or(old.path),
+        vars,
+        author: project_patch.author,
+        schema: None,
+    };
+    update(timestamp, project_info).await
+}
+
+/// setting or removing the schema of already existing project
+/// err indicates fs or json error
+/// stored vars have to follow the new schema
+pub async fn set_schema(timestamp: u128, project_schema: ProjectSchema) -> Result<WriteResult> {
+    let old = read(&project_schema.name).await?;
+    if old.is_none() {
+        return Ok(WriteResult::NotFound);
+    }
+    let old = old.unwrap();
+
+    if let Some(violations) = validate(&project_schema.schema, &old.vars) {
+        return Ok(WriteResult::Invalid(violations));
+    }
+
+    let data = ProjectEntry{
+        timestamp,
+        vars: old.vars,
+        path: old.path,
+        schema: project_schema.schema,
+    };
+    if !overwrite(&project_schema.name, &data)? {
+        return Ok(WriteResult::NotFound);
+    }
+    Ok(WriteResult::Done)
+}
+
+/// giving the project a new name, the entry is kept as it is
+/// err indicates fs error
+/// target has to be free, existing target is never overwritten
+pub async fn rename(project_name: &str, target_name: &str) -> Result<WriteResult> {
+    let data = read(project_name).await?;
+    if data.is_none() {
+        return Ok(WriteResult::NotFound);
+    }
+    if exists(target_name)? {
+        return Ok(WriteResult::Exists);
+    }
+
+    // target is written first so a crash in between never loses the entry
+    store(target_name, data.as_ref())?;
+    store(project_name, None)?;
+    Ok(WriteResult::Done)
+}
+
+/// making a new project with the same entry under a different name
+/// err indicates fs or json error
+/// target has to be free, existing target is never overwritten
+pub async fn copy(project_name: &str, target_name: &str) -> Result<WriteResult> {
+    let data = read(project_name).await?;
+    if data.is_none() {
+        return Ok(WriteResult::NotFound);
+    }
+    if exists(target_name)? {
+        return Ok(WriteResult::Exists);
+    }
+
+    store(target_name, data.as_ref())?;
+    Ok(WriteResult::Done)
 }
 
 /// delete already existing project
 /// err indicates fs error
 /// false means that it doesn't exit
 pub async fn delete(project_name: &str) -> Result<bool> {
-    let path = path_prefix!(project_name);
-    let res = remove_file(path);
-    if res.is_err() {
-        let err = res.err().unwrap();
-        match err.kind() {
-            std::io::ErrorKind::NotFound => return Ok(false),
-            _ => return Err(err.into()),
-        }
+    if !exists(project_name)? {
+        return Ok(false);
     }
+    store(project_name, None)?;
     Ok(true)
 }
 
+/// result of a batch operation that wrote to an entry
+fn operation_result(res: WriteResult, timestamp: u128, status: u16) -> OperationResult {
+    match res {
+        WriteResult::Done => OperationResult {
+            timestamp: Some(timestamp),
+            ..OperationResult::new(status)
+        },
+        WriteResult::Exists =>
+            OperationResult::failed(409, ApiError::new(ErrorCode::AlreadyExists, "project already exists")),
+        WriteResult::NotFound =>
+            OperationResult::failed(404, ApiError::new(ErrorCode::NotFound, "project not found")),
+        WriteResult::Invalid(violations) =>
+            OperationResult::failed(422, ApiError::schema_violation(violations)),
+    }
+}
+
+/// executing a single operation of a batch
+/// err indicates fs or json error
+async fn execute_operation(timestamp: u128, operation: BatchOperation) -> Result<OperationResult> {
+    let res = match operation {
+        BatchOperation::Read { name } => match read(&name).await? {
+            Some(entry) => OperationResult {
+                entry: Some(entry),
+                ..OperationResult::new(200)
+            },
+            None => OperationResult::failed(404, ApiError::new(ErrorCode::NotFound, "project not found")),
+        },
+        BatchOperation::Create(project) =>
+            operation_result(create(timestamp, project).await?, timestamp, 201),
+        BatchOperation::Update(project) =>
+            operation_result(update(timestamp, project).await?, timestamp, 200),
+        BatchOperation::Delete { name } => match delete(&name).await? {
+            true => OperationResult::new(204),
+            false => OperationResult::failed(404, ApiError::new(ErrorCode::NotFound, "project not found")),
+        },
+    };
+    Ok(res)
+}
+
+/// executing operations in order, failed operations don't stop the rest
+/// atomic batch stops at the first failure and undoes changes of the previous operations
+/// err indicates fs or json error, atomic batch is undone in that case as well
+pub async fn batch(timestamp: u128, batch: Batch) -> Result<BatchResponse> {
+    let total = batch.operations.len();
+    let mut results = Vec::with_capacity(total);
+    // entries as they were before the batch touched them
+    let mut snapshots: Vec<(String, Option<ProjectEntry>)> = Vec::new();
+    let mut failed = None;
+
+    for operation in batch.operations {
+        if batch.atomic && operation.is_write() {
+            let project_name = operation.project_name();
+            if !snapshots.iter().any(|(n, _)| n == project_name) {
+                let old = read(project_name).await?;
+                snapshots.push((project_name.to_string(), old));
+            }
+        }
+
+        let res = execute_operation(timestamp, operation).await;
+        let res = match res {
+            Ok(res) => res,
+            Err(err) => {
+                if batch.atomic {
+                    rollback(&snapshots)?;
+                }
+                return Err(err);
+            },
+        };
+
+        let success = res.error.is_none();
+        results.push(res);
+        if batch.atomic && !success {
+            failed = Some(results.len() - 1);
+            break;
+        }
+    }
+
+    let rolled_back = failed.is_some();
+    if let Some(failed) = failed {
+        rollback(&snapshots)?;
+
+        // every operation other than the failed one is reported as not applied
+        for (i, res) in results.iter_mut().enumerate() {
+            if i != failed {
+                *res = OperationResult::aborted();
+            }
+        }
+        results.resize(total, OperationResult::aborted());
+    }
+
+    Ok(BatchResponse {
+        rolled_back,
+        results,
+    })
+}
+
+/// restoring entries in the reverse order of taking the snapshots
+fn rollback(snapshots: &[(String, Option<ProjectEntry>)]) -> Result<()> {
+    for (project_name, old) in snapshots.iter().rev() {
+        // putting back the entry as it was before
+        store(project_name, old.as_ref())?;
+    }
+    Ok(())
+}
+
+/// setting the entry to the one on the primary, None removes it
+/// err indicates fs or json error
+pub async fn replicate(project_name: &str, data: Option<&ProjectEntry>) -> Result<()> {
+    store(project_name, data)
+}
+
+/// reading every entry, files starting with a dot are reserved and not entries
+/// err indicates fs or json error
+pub async fn backup(timestamp: u128) -> Result<Archive> {
+    let mut entries = BTreeMap::new();
+    for file in read_dir(path_prefix!(""))? {
+        let Ok(project_name) = file?.file_name().into_string() else {
+            continue;
+        };
+        if project_name.starts_with('.') {
+            continue;
+        }
+        if let Some(entry) = read(&project_name).await? {
+            entries.insert(project_name, entry);
+        }
+    }
+    Ok(Archive { timestamp, entries })
+}
+
+/// writing every entry of the archive, entries not in the archive are kept
+/// archive is checked as a whole before anything is written
+/// err indicates fs or json error
+pub async fn restore(archive: &Archive, policy: ConflictPolicy) -> Result<RestoreResult> {
+    for (project_name, entry) in archive.entries.iter() {
+        if let Some(violations) = validate(&entry.schema, &entry.vars) {
+            return Ok(RestoreResult::Invalid(project_name.clone(), violations));
+        }
+    }
+
+    let mut existing = Vec::new();
+    for project_name in archive.entries.keys() {
+        if exists(project_name)? {
+            existing.push(project_name.clone());
+        }
+    }
+    if policy == ConflictPolicy::Fail && !existing.is_empty() {
+        return Ok(RestoreResult::Conflicts(existing));
+    }
+
+    let mut res = RestoreResponse::default();
+    for (project_name, entry) in archive.entries.iter() {
+        if policy == ConflictPolicy::Skip && existing.contains(project_name) {
+            res.skipped.push(project_name.clone());
+            continue;
+        }
+        store(project_name, Some(entry))?;
+        res.restored.push(project_name.clone());
+    }
+    Ok(RestoreResult::Done(res))
+}
+
 #[cfg(test)]
 mod tests {
-    use senvy_common::types::Var;
+    use senvy_common::{types::Var, schema::{SchemaVar, VarType}};
     use super::*;
 
     #[actix_rt::test]
@@ -128,18 +527,20 @@ mod tests {
         let data = Project{
             name: "test-name".to_string(),
             vars: vec![
-                Var{name: "port".to_string(), value: "8080".to_string()}
+                Var::new("port".to_string(), "8080".to_string())
             ],
-            path: "./.env".to_string()
+            path: "./.env".to_string(),
+            author: None,
+            schema: None,
         };
         let res = create(123, data.clone()).await;
 
         // file that doesn't exist
-        assert_eq!(res.unwrap(), true);
+        assert_eq!(WriteResult::Done, res.unwrap());
 
         // creating already existing file
         let res = create(123, data).await;
-        assert_eq!(res.unwrap(), false);
+        assert_eq!(WriteResult::Exists, res.unwrap());
     }
 
     #[actix_rt::test]
@@ -147,17 +548,21 @@ mod tests {
         let data = Project{
             name: "test-read".to_string(),
             vars: vec![
-                Var{name: "port".to_string(), value: "8080".to_string()}
+                Var::new("port".to_string(), "8080".to_string())
             ],
-            path: "./.env".to_string()
+            path: "./.env".to_string(),
+            author: None,
+            schema: None,
         };
         _ = create(123, data.clone()).await.unwrap();
 
-        let data = ProjectEntry{
+        let mut data = ProjectEntry{
             timestamp: 123,
             vars: data.vars,
-            path: "./.env".to_string()
+            path: "./.env".to_string(),
+            schema: None,
         };
+        data.vars[0].modified = Some(123);
 
         // reading an existing file
         let res = read("test-read").await;
@@ -166,6 +571,10 @@ mod tests {
         // reading file that doesn't exist
         let res = read("test-read-not-existing").await;
         assert_eq!(None, res.unwrap());
+
+        // only the version
+        assert_eq!(Some(123), version("test-read").await.unwrap());
+        assert_eq!(None, version("test-read-not-existing").await.unwrap());
     }
 
     #[actix_rt::test]
@@ -173,49 +582,322 @@ mod tests {
         let mut data = Project{
             name: "test-update".to_string(),
             vars: vec![
-                Var{name: "port".to_string(), value: "8080".to_string()}
+                Var::new("port".to_string(), "8080".to_string())
             ],
-            path: "./.env".to_string()
+            path: "./.env".to_string(),
+            author: None,
+            schema: None,
         };
+        data.vars[0].description = Some("server port".to_string());
         _ = create(123, data.clone()).await.unwrap();
 
         // updating existing file
-        data.vars.push(Var{name: "new-var".to_string(), value: "new".to_string()});
+        data.author = Some("tester".to_string());
+        data.vars[0].description = None;
+        data.vars.push(Var::new("new-var".to_string(), "new".to_string()));
         let res = update(125, data.clone()).await.unwrap();
-        assert_eq!(res, true);
+        assert_eq!(WriteResult::Done, res);
 
         // checking if data was updated
+        // unchanged var keeps its stamp and metadata, new var is stamped with the author
         let read_data = read("test-update").await.unwrap();
-        let expected_data = ProjectEntry{
+        let mut expected_data = ProjectEntry{
             timestamp: 125,
             vars: data.vars.clone(),
-            path: "./.env".to_string()
+            path: "./.env".to_string(),
+            schema: None,
         };
+        expected_data.vars[0].description = Some("server port".to_string());
+        expected_data.vars[0].modified = Some(123);
+        expected_data.vars[1].modified = Some(125);
+        expected_data.vars[1].modified_by = Some("tester".to_string());
         assert_eq!(Some(expected_data), read_data);
 
         // updating file that doesn't exist
         data.name = "test-update-wrong-name".to_string();
         let res = update(125, data).await.unwrap();
-        assert_eq!(false, res);
+        assert_eq!(WriteResult::NotFound, res);
+    }
+
+    #[actix_rt::test]
+    async fn schema_file() {
+        // entry left by a failed run
+        _ = delete("test-schema").await;
+        let mut data = Project{
+            name: "test-schema".to_string(),
+            vars: vec![
+                Var::new("port".to_string(), "8080".to_string())
+            ],
+            path: "./.env".to_string(),
+            author: None,
+            schema: None,
+        };
+        _ = create(123, data.clone()).await.unwrap();
+
+        let schema = Schema {
+            vars: vec![SchemaVar {
+                name: "port".to_string(),
+                required: true,
+                kind: VarType::Port,
+                ..Default::default()
+            }],
+        };
+        let project_schema = ProjectSchema {
+            name: "test-schema".to_string(),
+            schema: Some(schema),
+        };
+        let res = set_schema(124, project_schema).await.unwrap();
+        assert_eq!(WriteResult::Done, res);
+
+        // stored schema is used when updating
+        data.vars[0].value = "not-a-port".to_string();
+        let res = update(125, data.clone()).await.unwrap();
+        assert!(matches!(res, WriteResult::Invalid(v) if v.len() == 1));
+
+        // removing the schema
+        let project_schema = ProjectSchema {
+            name: "test-schema".to_string(),
+            schema: None,
+        };
+        _ = set_schema(126, project_schema).await.unwrap();
+        let res = update(127, data).await.unwrap();
+        assert_eq!(WriteResult::Done, res);
+
+        delete("test-schema").await.unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn patch_file() {
+        // entry left by a failed run
+        _ = delete("test-patch").await;
+        let data = Project{
+            name: "test-patch".to_string(),
+            vars: vec![
+                Var::new("port".to_string(), "8080".to_string())
+            ],
+            path: "./.env".to_string(),
+            author: None,
+            schema: None,
+        };
+
+        // patching file that doesn't exist
+        let res = patch(123, "test-patch".to_string(), ProjectPatch::default()).await.unwrap();
+        assert_eq!(WriteResult::NotFound, res);
+
+        let (res, created) = put(123, data.clone()).await.unwrap();
+        assert_eq!((WriteResult::Done, true), (res, created));
+
+        // only the path is changed
+        let project_patch = ProjectPatch {
+            path: Some("./folder/.env".to_string()),
+            ..Default::default()
+        };
+        let res = patch(125, "test-patch".to_string(), project_patch).await.unwrap();
+        assert_eq!(WriteResult::Done, res);
+
+        let read_data = read("test-patch").await.unwrap().unwrap();
+        assert_eq!("./folder/.env", read_data.path);
+        assert_eq!(data.vars[0].value, read_data.vars[0].value);
+        assert_eq!(Some(123), read_data.vars[0].modified);
+
+        // setting and unsetting single vars
+        let project_patch = ProjectPatch {
+            set: vec![Var::new("host".to_string(), "localhost".to_string())],
+            unset: vec!["port".to_string(), "missing".to_string()],
+            ..Default::default()
+        };
+        let res = patch(127, "test-patch".to_string(), project_patch).await.unwrap();
+        assert_eq!(WriteResult::Done, res);
+
+        let read_data = read("test-patch").await.unwrap().unwrap();
+        assert_eq!(1, read_data.vars.len());
+        assert_eq!("host", read_data.vars[0].name);
+        assert_eq!(Some(127), read_data.vars[0].modified);
+
+        delete("test-patch").await.unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn sync_files() {
+        let data = Project{
+            name: "test-sync".to_string(),
+            vars: Vec::new(),
+            path: "./.env".to_string(),
+            author: None,
+            schema: None,
+        };
+        _ = create(123, data).await.unwrap();
+        sync().unwrap();
+
+        delete("test-sync").await.unwrap();
     }
 
     #[actix_rt::test]
     async fn delete_file() {
         // deleting file that doesn't exist
         let res = delete("test-delete").await.unwrap();
-        assert_eq!(false, res);
+        assert!(!res);
 
         let data = Project{
             name: "test-delete".to_string(),
             vars: vec![
-                Var{name: "port".to_string(), value: "8080".to_string()}
+                Var::new("port".to_string(), "8080".to_string())
             ],
-            path: "./.env".to_string()
+            path: "./.env".to_string(),
+            author: None,
+            schema: None,
         };
         _ = create(123, data.clone()).await.unwrap();
 
         // deleting file that does exist
         let res = delete("test-delete").await.unwrap();
-        assert_eq!(true, res);
+        assert!(res);
+    }
+
+    #[actix_rt::test]
+    async fn backup_restore_files() {
+        let data = Project{
+            name: "test-backup".to_string(),
+            vars: vec![
+                Var::new("port".to_string(), "8080".to_string())
+            ],
+            path: "./.env".to_string(),
+            author: None,
+            schema: None,
+        };
+        _ = create(123, data.clone()).await.unwrap();
+
+        let archive = backup(124).await.unwrap();
+        let entry = archive.entries.get("test-backup").unwrap().clone();
+        assert!(!archive.entries.keys().any(|k| k.starts_with('.')));
+
+        // only the entries of this test, others are changed by the tests running in parallel
+        let mut archive = Archive { timestamp: 124, entries: BTreeMap::new() };
+        archive.entries.insert("test-backup".to_string(), entry.clone());
+        archive.entries.insert("test-backup-new".to_string(), entry.clone());
+        _ = delete("test-backup-new").await.unwrap();
+
+        // fail policy doesn't restore anything if an entry exists
+        let res = restore(&archive, ConflictPolicy::Fail).await.unwrap();
+        assert_eq!(RestoreResult::Conflicts(vec!["test-backup".to_string()]), res);
+        assert_eq!(None, read("test-backup-new").await.unwrap());
+
+        let res = restore(&archive, ConflictPolicy::Skip).await.unwrap();
+        assert_eq!(RestoreResult::Done(RestoreResponse {
+            restored: vec!["test-backup-new".to_string()],
+            skipped: vec!["test-backup".to_string()],
+        }), res);
+        assert_eq!(Some(entry.clone()), read("test-backup-new").await.unwrap());
+
+        let res = restore(&archive, ConflictPolicy::Overwrite).await.unwrap();
+        assert_eq!(RestoreResult::Done(RestoreResponse {
+            restored: vec!["test-backup".to_string(), "test-backup-new".to_string()],
+            skipped: Vec::new(),
+        }), res);
+
+        _ = delete("test-backup").await.unwrap();
+        _ = delete("test-backup-new").await.unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn batch_files() {
+        // entries left by a failed run
+        for project_name in ["test-batch", "test-batch-new"] {
+            _ = delete(project_name).await;
+        }
+        let data = Project{
+            name: "test-batch".to_string(),
+            vars: vec![
+                Var::new("port".to_string(), "8080".to_string())
+            ],
+            path: "./.env".to_string(),
+            author: None,
+            schema: None,
+        };
+        _ = create(123, data.clone()).await.unwrap();
+
+        // failed operation doesn't stop the rest
+        let mut changed = data.clone();
+        changed.vars[0].value = "9090".to_string();
+        let res = batch(124, Batch {
+            operations: vec![
+                BatchOperation::Create(data.clone()),
+                BatchOperation::Update(changed.clone()),
+                BatchOperation::Read { name: "test-batch".to_string() },
+            ],
+            atomic: false,
+        }).await.unwrap();
+        assert!(!res.rolled_back);
+        assert_eq!(vec![409, 200, 200], res.results.iter().map(|r| r.status).collect::<Vec<_>>());
+        assert_eq!(Some(ErrorCode::AlreadyExists), res.results[0].error.as_ref().map(|e| e.code));
+        assert_eq!("9090", res.results[2].entry.as_ref().unwrap().vars[0].value);
+
+        // atomic batch is undone after the failure
+        let mut new = data.clone();
+        new.name = "test-batch-new".to_string();
+        let res = batch(125, Batch {
+            operations: vec![
+                BatchOperation::Create(new),
+                BatchOperation::Delete { name: "test-batch".to_string() },
+                BatchOperation::Delete { name: "test-batch-missing".to_string() },
+                BatchOperation::Read { name: "test-batch".to_string() },
+            ],
+            atomic: true,
+        }).await.unwrap();
+        assert!(res.rolled_back);
+        assert_eq!(vec![424, 424, 404, 424], res.results.iter().map(|r| r.status).collect::<Vec<_>>());
+        assert_eq!(None, read("test-batch-new").await.unwrap());
+        let read_data = read("test-batch").await.unwrap().unwrap();
+        assert_eq!(124, read_data.timestamp);
+
+        delete("test-batch").await.unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn rename_copy_file() {
+        // entries left by a failed run
+        for project_name in ["test-rename", "test-renamed", "test-renamed-again", "test-copied"] {
+            _ = delete(project_name).await;
+        }
+        let data = Project{
+            name: "test-rename".to_string(),
+            vars: vec![
+                Var::new("port".to_string(), "8080".to_string())
+            ],
+            path: "./.env".to_string(),
+            author: None,
+            schema: None,
+        };
+        _ = create(123, data.clone()).await.unwrap();
+
+        // renaming keeps the entry and frees the old name
+        let res = rename("test-rename", "test-renamed").await.unwrap();
+        assert_eq!(WriteResult::Done, res);
+        assert_eq!(None, read("test-rename").await.unwrap());
+        let renamed = read("test-renamed").await.unwrap().unwrap();
+        assert_eq!(123, renamed.timestamp);
+
+        // renaming project that doesn't exist
+        let res = rename("test-rename", "test-renamed-again").await.unwrap();
+        assert_eq!(WriteResult::NotFound, res);
+
+        // copy is independent of the original
+        let res = copy("test-renamed", "test-copied").await.unwrap();
+        assert_eq!(WriteResult::Done, res);
+        let mut changed = data.clone();
+        changed.name = "test-copied".to_string();
+        changed.vars[0].value = "9090".to_string();
+        _ = update(125, changed).await.unwrap();
+        assert_eq!(renamed, read("test-renamed").await.unwrap().unwrap());
+
+        // existing target is never overwritten
+        let res = copy("test-renamed", "test-copied").await.unwrap();
+        assert_eq!(WriteResult::Exists, res);
+        let res = rename("test-copied", "test-renamed").await.unwrap();
+        assert_eq!(WriteResult::Exists, res);
+        assert_eq!("9090", read("test-copied").await.unwrap().unwrap().vars[0].value);
+
+        delete("test-renamed").await.unwrap();
+        delete("test-copied").await.unwrap();
     }
 }
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use log::error;
+use actix_web::{
+    web::{self, Data},
+    get,
+    Responder, HttpResponse
+};
+use senvy_common::error::{ApiError, ErrorCode};
+use crate::{
+    queue::FileTaskQueue,
+    metrics::Metrics,
+    files
+};
+
+/// process is up and serving requests
+#[get("/healthz")]
+async fn healthz() -> impl Responder {
+    HttpResponse::Ok().body("ok")
+}
+
+/// worker thread is running and entries can be written
+#[get("/readyz")]
+async fn readyz(metrics: Data<Arc<Metrics>>) -> impl Responder {
+    if !metrics.worker_alive() {
+        return HttpResponse::ServiceUnavailable().json(ApiError::new(ErrorCode::Unavailable, "worker is not running"));
+    }
+
+    let writable = web::block(files::writable).await;
+    match writable {
+        Ok(Ok(())) => HttpResponse::Ok().body("ready"),
+        Ok(Err(err)) => {
+            error!("Error checking if data directory is writable: {}", err);
+            HttpResponse::ServiceUnavailable().json(ApiError::new(ErrorCode::Unavailable, "data directory is not writable"))
+        },
+        Err(_) => HttpResponse::ServiceUnavailable().json(ApiError::new(ErrorCode::Unavailable, "data directory check failed")),
+    }
+}
+
+/// prometheus metrics
+#[get("/metrics")]
+async fn prometheus(metrics: Data<Arc<Metrics>>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics.render(queue.depth()))
+}
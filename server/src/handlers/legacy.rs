@@ -0,0 +1,166 @@
+use std::sync::Arc;
+use log::error;
+use actix_web::{
+    web::{Json, Data},
+    get, post, delete,
+    Responder, HttpResponse, HttpRequest
+};
+use senvy_common::{
+    types::{Project, ProjectSchema},
+    error::{ApiError, ErrorCode}
+};
+use crate::{
+    queue::FileTaskQueue,
+    files::WriteResult
+};
+use super::{timestamp, valid_name, internal_error_response, entry_response};
+
+#[post("/new")]
+async fn new(project: Json<Project>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let timestamp = timestamp();
+    let project = project.into_inner();
+    if !valid_name(&project.name) {
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::InvalidName, "invalid project name"));
+    }
+
+    let res = execute_task!(queue, CreateConfig, CreateReturn, timestamp, project);
+
+    // if there was error during creation check what happened exactly
+    if res.is_err() {
+        // first element in the chain is the original error
+        let err = get_err!(res);
+        if let Some(err) = err {
+            error!("Error creating a new project: {}", err);
+        }else {
+            error!("Error creating a new project: no error");
+        }
+        return internal_error_response();
+    }
+
+    match res.unwrap() {
+        WriteResult::Done => HttpResponse::Ok().body(format!("{}", timestamp)),
+        WriteResult::Invalid(violations) => HttpResponse::BadRequest().json(ApiError::schema_violation(violations)),
+        _ => HttpResponse::BadRequest().json(ApiError::new(ErrorCode::AlreadyExists, "project already exists")),
+    }
+}
+
+#[get("/read")]
+async fn read(req: HttpRequest, project_name: String, queue: Data<Arc<FileTaskQueue>>) -> impl Responder{
+    if !valid_name(&project_name) {
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::InvalidName, "invalid project name"));
+    }
+    let data = execute_task!(queue, ReadConfig, ReadReturn, project_name);
+    if data.is_err() {
+        // json is checked when written so it can only be fs error
+        let err = get_err!(data);
+        if let Some(err) = err {
+            error!("Error reading a project: {}", err);
+        }else {
+            error!("Error reading a project: no error");
+        }
+        return internal_error_response();
+    }
+
+    let data = data.unwrap();
+    if data.is_none() {
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::NotFound, "project does not exist"));
+    }
+
+    let data = data.unwrap();
+    entry_response(&req, data)
+}
+
+#[post("/update")]
+async fn update(project: Json<Project>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let timestamp = timestamp();
+    let project = project.into_inner();
+    if !valid_name(&project.name) {
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::InvalidName, "invalid project name"));
+    }
+
+    let res = execute_task!(queue, UpdateConfig, UpdateReturn, timestamp, project);
+    if res.is_err() {
+        let err = get_err!(res);
+        if let Some(err) = err {
+            error!("Error updating a project: {}", err);
+        }else {
+            error!("Error updating a project: no error");
+        }
+        return internal_error_response();
+    }
+    match res.unwrap() {
+        WriteResult::Done => HttpResponse::Ok().body(format!("{}", timestamp)),
+        WriteResult::Invalid(violations) => HttpResponse::BadRequest().json(ApiError::schema_violation(violations)),
+        _ => HttpResponse::BadRequest().json(ApiError::new(ErrorCode::NotFound, "project does not exist")),
+    }
+}
+
+#[post("/schema")]
+async fn schema(project_schema: Json<ProjectSchema>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let timestamp = timestamp();
+    let project_schema = project_schema.into_inner();
+    if !valid_name(&project_schema.name) {
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::InvalidName, "invalid project name"));
+    }
+
+    let res = execute_task!(queue, SetSchemaConfig, SetSchemaReturn, timestamp, project_schema);
+    if res.is_err() {
+        let err = get_err!(res);
+        if let Some(err) = err {
+            error!("Error setting schema of a project: {}", err);
+        }else {
+            error!("Error setting schema of a project: no error");
+        }
+        return internal_error_response();
+    }
+    match res.unwrap() {
+        WriteResult::Done => HttpResponse::Ok().body(format!("{}", timestamp)),
+        WriteResult::Invalid(violations) => HttpResponse::BadRequest().json(ApiError::schema_violation(violations)),
+        _ => HttpResponse::BadRequest().json(ApiError::new(ErrorCode::NotFound, "project does not exist")),
+    }
+}
+
+#[delete("/delete")]
+async fn delete(project_name: String, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    if !valid_name(&project_name) {
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::InvalidName, "invalid project name"));
+    }
+    let res = execute_task!(queue, DeleteConfig, DeleteReturn, project_name);
+    if res.is_err() {
+        let err = get_err!(res);
+        if let Some(err) = err {
+            error!("Error updating a project: {}", err);
+        }else {
+            error!("Error updating a project: no error");
+        }
+        return internal_error_response();
+    }
+    let res = res.unwrap();
+    if !res {
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::NotFound, "project does not exist"));
+    }
+    HttpResponse::Ok().finish()
+}
+
+#[get("/exists")]
+async fn exists(project_name: String, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    if !valid_name(&project_name) {
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::InvalidName, "invalid project name"));
+    }
+    let res = execute_task!(queue, ExistsConfig, ExistsReturn, project_name);
+    if res.is_err() {
+        let err = get_err!(res);
+        if let Some(err) = err {
+            error!("Error checking if a project exists: {}", err);
+        }else {
+            error!("Error checking if a project exists: no error");
+        }
+        return internal_error_response();
+    }
+
+    let res = res.unwrap();
+    if res.is_none() {
+        return HttpResponse::Ok().body("false");
+    }
+    HttpResponse::Ok().body("true")
+}
@@ -0,0 +1,195 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use actix_web::{
+    HttpResponse, HttpRequest,
+    http::header::{self, Header, HeaderMap, IfNoneMatch}
+};
+use senvy_common::{
+    error::{ApiError, ErrorCode},
+    types::{ProjectEntry, etag}
+};
+use crate::queue::QueueError;
+
+macro_rules! get_err {
+    ( $x:expr ) => {
+        {
+            $x.as_ref()
+                .err()
+                .unwrap()
+                .chain()
+                .next()
+        }
+    };
+}
+
+// unreachable in match for a specific task result is used to uncover mismatched result types
+/// arguments -> job queue, task type, task return type,
+/// and the rest of the provided arguments are for the underlying file function
+macro_rules! execute_task {
+    ( $queue:ident, $task_type:ident, $task_return_type:ident, $($arg:ident),+ ) => {
+        {
+            // pushing a new task into the queue and awaiting the result
+            let res = $queue.execute($crate::queue::FileTask::$task_type($($arg),+)).await;
+
+            // was there error receiving result
+            let res = match res {
+                Ok(res) => res,
+                Err(err) => {
+                    return $crate::handlers::queue_error_response(err);
+                }
+            };
+
+            // match the result into the desired
+            let res = match res {
+                $crate::queue::task::FileTaskReturnType::$task_return_type(r) => {r},
+                _ => unreachable!(),
+            };
+            res
+        }
+    }
+}
+
+/// logs the original error of a failed task and returns internal server error from the handler
+/// arguments -> task result, description of the action for the log
+macro_rules! internal_error {
+    ( $res:ident, $action:expr ) => {
+        if $res.is_err() {
+            // first element in the chain is the original error
+            let err = get_err!($res);
+            if let Some(err) = err {
+                log::error!("Error {}: {}", $action, err);
+            }else {
+                log::error!("Error {}: no error", $action);
+            }
+            return $crate::handlers::internal_error_response();
+        }
+    };
+}
+
+pub mod health;
+pub mod legacy;
+pub mod v1;
+
+/// timestamp of request arrival in nanoseconds
+pub fn timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap() // safe to just unwrap beacuse UNIX_EPOCH is passed
+        .as_nanos()
+}
+
+/// project names are used as file names so they can't point outside of the data directory
+/// names starting with a dot are reserved for the server
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 255
+        && !name.starts_with('.')
+        && !name.contains(|c: char| c == '/' || c == '\\' || c.is_control())
+}
+
+/// token of the admin endpoints, they are disabled when there is none
+pub struct AdminToken(Option<String>);
+
+impl AdminToken {
+    pub fn new(token: Option<String>) -> Self {
+        AdminToken(token.filter(|t| !t.is_empty()))
+    }
+
+    /// request carries the admin token in the authorization header
+    pub fn authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(token) = &self.0 else {
+            return false;
+        };
+        let sent = headers.get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        // compared in constant time so the token can't be guessed from response times
+        sent.len() == token.len()
+            && sent.bytes().zip(token.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+    }
+
+    /// forbidden response for requests to admin endpoints without the token
+    pub fn check(&self, req: &HttpRequest) -> Option<HttpResponse> {
+        if self.0.is_none() {
+            return Some(HttpResponse::Forbidden()
+                .json(ApiError::new(ErrorCode::Forbidden, "admin endpoints are disabled, ADMIN_TOKEN of the server is not set")));
+        }
+        if !self.authorized(req.headers()) {
+            return Some(HttpResponse::Forbidden()
+                .json(ApiError::new(ErrorCode::Forbidden, "missing or wrong admin token")));
+        }
+        None
+    }
+}
+
+/// entry with its etag, or not modified if the client already has this version
+pub fn entry_response(req: &HttpRequest, entry: ProjectEntry) -> HttpResponse {
+    // weak comparison, tags differ only by the version
+    let version = entry.timestamp.to_string();
+    let not_modified = match IfNoneMatch::parse(req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|t| t.tag() == version),
+        Err(_) => false,
+    };
+    let tag = etag(entry.timestamp);
+    if not_modified {
+        return HttpResponse::NotModified()
+            .insert_header((header::ETAG, tag))
+            .finish();
+    }
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, tag))
+        .json(entry)
+}
+
+/// task didn't return a result, only a dropped task is an internal error
+pub fn queue_error_response(err: QueueError) -> HttpResponse {
+    match err {
+        QueueError::Dropped => {
+            log::error!("Error executing a task: {}", err);
+            internal_error_response()
+        },
+        QueueError::TimedOut => HttpResponse::ServiceUnavailable()
+            .json(ApiError::new(ErrorCode::Unavailable, "task timed out, it might still be applied")),
+        QueueError::ReadOnly => HttpResponse::MethodNotAllowed()
+            .json(ApiError::new(ErrorCode::ReadOnly, &err.to_string())),
+        _ => HttpResponse::ServiceUnavailable()
+            .json(ApiError::new(ErrorCode::Unavailable, &err.to_string())),
+    }
+}
+
+/// client has to wait before sending another request, the wait is rounded up to seconds
+pub fn rate_limited_response(wait: Duration) -> HttpResponse {
+    let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    HttpResponse::TooManyRequests()
+        .insert_header((header::RETRY_AFTER, secs.to_string()))
+        .json(ApiError::new(ErrorCode::RateLimited, &format!("too many requests, retry in {}s", secs)))
+}
+
+/// details of internal errors are only logged, the client gets a generic error
+pub fn internal_error_response() -> HttpResponse {
+    HttpResponse::InternalServerError().json(ApiError::new(ErrorCode::Internal, "internal server error"))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use super::*;
+
+    #[test]
+    fn admin_token() {
+        let req = TestRequest::get().insert_header(("Authorization", "Bearer secret")).to_http_request();
+        let wrong = TestRequest::get().insert_header(("Authorization", "Bearer secreT")).to_http_request();
+        let missing = TestRequest::get().to_http_request();
+
+        let admin = AdminToken::new(Some("secret".to_string()));
+        assert!(admin.check(&req).is_none());
+        assert!(admin.check(&wrong).is_some());
+        assert!(admin.check(&missing).is_some());
+
+        // disabled without a token, an empty header doesn't match an empty token
+        let admin = AdminToken::new(Some(String::new()));
+        assert!(admin.check(&missing).is_some());
+        assert!(AdminToken::new(None).check(&req).is_some());
+    }
+}
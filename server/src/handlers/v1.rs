@@ -0,0 +1,323 @@
+use std::{sync::Arc, time::Duration};
+use actix_web::{
+    web::{Json, Data, Path, Query, Bytes},
+    get, put, patch, delete, head, post,
+    http::header::{self, HeaderName, CacheDirective},
+    Responder, HttpResponse, HttpRequest
+};
+use futures_util::stream;
+use serde_derive::Deserialize;
+use tokio::{sync::broadcast::error::RecvError, time::timeout};
+use senvy_common::{
+    types::{Archive, Batch, ConflictPolicy, Project, ProjectPatch, ProjectSchema, ProjectTarget, EntryVersion, etag},
+    schema::Schema,
+    error::{ApiError, ErrorCode}
+};
+use crate::{
+    queue::FileTaskQueue,
+    files::{WriteResult, RestoreResult},
+    events::Events
+};
+
+/// comment sent on idle event streams so proxies don't close them
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+use super::{timestamp, valid_name, entry_response, AdminToken};
+
+/// takes the project name out of the path
+/// returns bad request from the handler if the name is not valid
+macro_rules! project_name {
+    ( $x:ident ) => {
+        {
+            let name = $x.into_inner();
+            if !valid_name(&name) {
+                return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::InvalidName, "invalid project name"));
+            }
+            name
+        }
+    };
+}
+
+/// whether the conditional header is present with the '*' value
+fn matches_any(req: &HttpRequest, name: HeaderName) -> bool {
+    req.headers()
+        .get(name)
+        .map(|v| v == "*")
+        .unwrap_or(false)
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "project does not exist"))
+}
+
+/// response for a finished write, created is only used for the success status
+fn write_response(res: WriteResult, timestamp: u128, created: bool) -> HttpResponse {
+    match res {
+        WriteResult::Done if created => HttpResponse::Created().json(EntryVersion{timestamp}),
+        WriteResult::Done => HttpResponse::Ok().json(EntryVersion{timestamp}),
+        WriteResult::Exists => HttpResponse::PreconditionFailed().json(ApiError::new(ErrorCode::AlreadyExists, "project already exists")),
+        WriteResult::NotFound => not_found(),
+        WriteResult::Invalid(violations) => HttpResponse::UnprocessableEntity().json(ApiError::schema_violation(violations)),
+    }
+}
+
+#[get("/projects/{name}")]
+async fn read(req: HttpRequest, name: Path<String>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let project_name = project_name!(name);
+    let data = execute_task!(queue, ReadConfig, ReadReturn, project_name);
+    internal_error!(data, "reading a project");
+
+    match data.unwrap() {
+        Some(data) => entry_response(&req, data),
+        None => not_found(),
+    }
+}
+
+#[head("/projects/{name}")]
+async fn exists(name: Path<String>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let project_name = project_name!(name);
+    let res = execute_task!(queue, ExistsConfig, ExistsReturn, project_name);
+    internal_error!(res, "checking if a project exists");
+
+    match res.unwrap() {
+        Some(timestamp) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag(timestamp)))
+            .finish(),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+/// creates or replaces the project
+/// 'If-None-Match: *' only creates and 'If-Match: *' only replaces an existing project
+#[put("/projects/{name}")]
+async fn put(req: HttpRequest, name: Path<String>, project: Json<Project>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let project_name = project_name!(name);
+    let timestamp = timestamp();
+    let mut project = project.into_inner();
+    project.name = project_name;
+
+    if matches_any(&req, header::IF_NONE_MATCH) {
+        let res = execute_task!(queue, CreateConfig, CreateReturn, timestamp, project);
+        internal_error!(res, "creating a new project");
+        return write_response(res.unwrap(), timestamp, true);
+    }
+
+    if matches_any(&req, header::IF_MATCH) {
+        let res = execute_task!(queue, UpdateConfig, UpdateReturn, timestamp, project);
+        internal_error!(res, "updating a project");
+        return match res.unwrap() {
+            WriteResult::NotFound => HttpResponse::PreconditionFailed().json(ApiError::new(ErrorCode::NotFound, "project does not exist")),
+            res => write_response(res, timestamp, false),
+        };
+    }
+
+    let res = execute_task!(queue, PutConfig, PutReturn, timestamp, project);
+    internal_error!(res, "putting a project");
+    let (res, created) = res.unwrap();
+    write_response(res, timestamp, created)
+}
+
+#[patch("/projects/{name}")]
+async fn patch(name: Path<String>, project_patch: Json<ProjectPatch>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let project_name = project_name!(name);
+    let timestamp = timestamp();
+    let project_patch = project_patch.into_inner();
+
+    let res = execute_task!(queue, PatchConfig, PatchReturn, timestamp, project_name, project_patch);
+    internal_error!(res, "patching a project");
+    write_response(res.unwrap(), timestamp, false)
+}
+
+#[delete("/projects/{name}")]
+async fn delete(name: Path<String>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let project_name = project_name!(name);
+    let res = execute_task!(queue, DeleteConfig, DeleteReturn, project_name);
+    internal_error!(res, "deleting a project");
+
+    if !res.unwrap() {
+        return not_found();
+    }
+    HttpResponse::NoContent().finish()
+}
+
+#[put("/projects/{name}/schema")]
+async fn put_schema(name: Path<String>, schema: Json<Schema>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let project_name = project_name!(name);
+    let timestamp = timestamp();
+    let project_schema = ProjectSchema {
+        name: project_name,
+        schema: Some(schema.into_inner()),
+    };
+
+    let res = execute_task!(queue, SetSchemaConfig, SetSchemaReturn, timestamp, project_schema);
+    internal_error!(res, "setting schema of a project");
+    write_response(res.unwrap(), timestamp, false)
+}
+
+#[delete("/projects/{name}/schema")]
+async fn delete_schema(name: Path<String>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let project_name = project_name!(name);
+    let timestamp = timestamp();
+    let project_schema = ProjectSchema {
+        name: project_name,
+        schema: None,
+    };
+
+    let res = execute_task!(queue, SetSchemaConfig, SetSchemaReturn, timestamp, project_schema);
+    internal_error!(res, "removing schema of a project");
+    write_response(res.unwrap(), timestamp, false)
+}
+
+/// response for a rename or copy, existing target is a conflict since nothing was conditional
+fn move_response(res: WriteResult, success: HttpResponse) -> HttpResponse {
+    match res {
+        WriteResult::Done => success,
+        WriteResult::Exists => HttpResponse::Conflict().json(ApiError::new(ErrorCode::AlreadyExists, "target project already exists")),
+        WriteResult::NotFound => not_found(),
+        // renaming and copying don't touch the vars
+        WriteResult::Invalid(_) => unreachable!(),
+    }
+}
+
+/// moves the entry to a new name, version and vars are kept
+#[post("/projects/{name}/rename")]
+async fn rename(name: Path<String>, target: Json<ProjectTarget>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let project_name = project_name!(name);
+    let target_name = target.into_inner().name;
+    if !valid_name(&target_name) {
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::InvalidName, "invalid target project name"));
+    }
+
+    let res = execute_task!(queue, RenameConfig, RenameReturn, project_name, target_name);
+    internal_error!(res, "renaming a project");
+    move_response(res.unwrap(), HttpResponse::NoContent().finish())
+}
+
+/// makes a new entry with the same vars under the target name
+#[post("/projects/{name}/copy")]
+async fn copy(name: Path<String>, target: Json<ProjectTarget>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let project_name = project_name!(name);
+    let target_name = target.into_inner().name;
+    if !valid_name(&target_name) {
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::InvalidName, "invalid target project name"));
+    }
+
+    let res = execute_task!(queue, CopyConfig, CopyReturn, project_name, target_name);
+    internal_error!(res, "copying a project");
+    move_response(res.unwrap(), HttpResponse::Created().finish())
+}
+
+/// operations on several projects executed as a single queued task
+/// results are returned per operation, atomic batch is undone after the first failure
+/// registered as a resource in main since it needs a larger json limit
+pub async fn batch(batch: Json<Batch>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    let batch = batch.into_inner();
+    // whole batch is rejected before queueing so an invalid name can't leave it half applied
+    let invalid = batch.operations.iter().position(|o| !valid_name(o.project_name()));
+    if let Some(i) = invalid {
+        let message = format!("invalid project name in operation {}", i);
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::InvalidName, &message));
+    }
+
+    let timestamp = timestamp();
+    let res = execute_task!(queue, BatchConfig, BatchReturn, timestamp, batch);
+    internal_error!(res, "executing a batch");
+    HttpResponse::Ok().json(res.unwrap())
+}
+
+/// snapshot of every entry, taken after the writes before it finished and before any write after it
+#[get("/admin/backup")]
+async fn backup(req: HttpRequest, admin: Data<AdminToken>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    if let Some(res) = admin.check(&req) {
+        return res;
+    }
+    let timestamp = timestamp();
+    let res = execute_task!(queue, BackupConfig, BackupReturn, timestamp);
+    internal_error!(res, "backing up entries");
+    HttpResponse::Ok().json(res.unwrap())
+}
+
+#[derive(Deserialize)]
+pub struct RestoreQuery {
+    /// what happens to entries that already exist
+    #[serde(default)]
+    policy: ConflictPolicy,
+}
+
+/// writes every entry of the archive, registered in main with the batch body limit
+pub async fn restore(req: HttpRequest, archive: Json<Archive>, query: Query<RestoreQuery>, admin: Data<AdminToken>, queue: Data<Arc<FileTaskQueue>>) -> impl Responder {
+    if let Some(res) = admin.check(&req) {
+        return res;
+    }
+    let archive = archive.into_inner();
+    let policy = query.into_inner().policy;
+    // whole archive is rejected before queueing so an invalid name can't leave it half restored
+    let invalid = archive.entries.keys().find(|n| !valid_name(n));
+    if let Some(project_name) = invalid {
+        let message = format!("invalid project name in archive: {}", project_name);
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::InvalidName, &message));
+    }
+
+    let res = execute_task!(queue, RestoreConfig, RestoreReturn, archive, policy);
+    internal_error!(res, "restoring entries");
+    match res.unwrap() {
+        RestoreResult::Done(res) => HttpResponse::Ok().json(res),
+        RestoreResult::Conflicts(existing) => {
+            let message = format!("projects already exist: {}", existing.join(", "));
+            HttpResponse::Conflict().json(ApiError::new(ErrorCode::AlreadyExists, &message))
+        },
+        RestoreResult::Invalid(project_name, violations) => {
+            let mut err = ApiError::schema_violation(violations);
+            err.message = format!("vars of {} do not follow the schema", project_name);
+            HttpResponse::UnprocessableEntity().json(err)
+        },
+    }
+}
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    /// only stream changes of this project
+    project: Option<String>,
+}
+
+/// server-sent events stream of entry changes
+/// a lagged event means that some changes were dropped and entries should be read again
+#[get("/events")]
+async fn events(query: Query<EventsQuery>, events: Data<Events>) -> impl Responder {
+    let project = query.into_inner().project;
+    let rx = events.subscribe();
+    let closed = events.closed();
+
+    let stream = stream::unfold((rx, closed, project), |(mut rx, mut closed, project)| async move {
+        loop {
+            if *closed.borrow() {
+                return None;
+            }
+            let msg = tokio::select! {
+                _ = closed.changed() => return None,
+                res = timeout(KEEP_ALIVE, rx.recv()) => match res {
+                    Err(_) => ": keep-alive\n\n".to_string(),
+                    Ok(Ok(event)) => {
+                        if project.as_ref().map(|p| *p != event.project).unwrap_or(false) {
+                            continue;
+                        }
+                        // event is made only out of strings and numbers so it always serializes
+                        let data = serde_json::to_string(&event).unwrap();
+                        format!("event: change\ndata: {}\n\n", data)
+                    },
+                    Ok(Err(RecvError::Lagged(n))) => format!("event: lagged\ndata: {}\n\n", n),
+                    Ok(Err(RecvError::Closed)) => return None,
+                },
+            };
+            return Some((Ok::<_, actix_web::Error>(Bytes::from(msg)), (rx, closed, project)));
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(header::CacheControl(vec![CacheDirective::NoCache]))
+        // compressed events would be held back by the encoder until enough of them pile up
+        .insert_header(header::ContentEncoding::Identity)
+        // connection isn't reused so a closed stream doesn't keep a stopping server busy
+        .force_close()
+        .streaming(stream)
+}
@@ -0,0 +1,189 @@
+use std::{
+    fs::{OpenOptions, File, remove_file},
+    io::{Write, BufRead, BufReader, ErrorKind},
+    sync::{Mutex, RwLock, RwLockReadGuard}
+};
+use anyhow::{Result, anyhow};
+use serde_derive::{Serialize, Deserialize};
+use serde_json::{to_vec, from_str};
+use senvy_common::types::ProjectEntry;
+use crate::files;
+
+/// journal of the entry files, names starting with a dot can't be project names
+static JOURNAL: Journal = Journal::new("data/.journal");
+
+/// change of a single entry, None means that the entry was removed
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Record {
+    project: String,
+    entry: Option<ProjectEntry>,
+}
+
+/// write-ahead log of the entry files
+/// every change is on the disk in the journal before it is applied to the entry file
+/// so a change cut off by a crash is applied again on the next start
+pub struct Journal {
+    path: &'static str,
+    /// opened on the first record
+    file: Mutex<Option<File>>,
+    /// held for reading while a change is applied, checkpoint holds it for writing
+    applying: RwLock<()>,
+}
+
+/// the journal used for the entry files
+pub fn journal() -> &'static Journal {
+    &JOURNAL
+}
+
+impl Journal {
+    pub const fn new(path: &'static str) -> Self {
+        Journal {
+            path,
+            file: Mutex::new(None),
+            applying: RwLock::new(()),
+        }
+    }
+
+    /// changes the entry file after the change is recorded, None removes the entry
+    pub fn write(&self, project_name: &str, entry: Option<&ProjectEntry>) -> Result<()> {
+        let _applying = self.record(project_name, entry)?;
+        write_entry(project_name, entry)
+    }
+
+    /// appends the change and flushes it to the disk
+    /// the change has to be applied to the entry file while the returned guard is held
+    fn record(&self, project_name: &str, entry: Option<&ProjectEntry>) -> Result<RwLockReadGuard<'_, ()>> {
+        let applying = self.applying.read().map_err(|_| anyhow!("journal lock poisoned"))?;
+
+        let mut line = to_vec(&Record {
+            project: project_name.to_string(),
+            entry: entry.cloned(),
+        })?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().map_err(|_| anyhow!("journal lock poisoned"))?;
+        if file.is_none() {
+            *file = Some(OpenOptions::new().create(true).append(true).open(self.path)?);
+        }
+        let file = file.as_mut().unwrap();
+        file.write_all(&line)?;
+        file.sync_data()?;
+        Ok(applying)
+    }
+
+    /// applies every change in the journal to the entry files and checkpoints
+    /// changes are applied in order so applying an already applied change again doesn't matter
+    /// a malformed last line is a record cut off by a crash, its change was never applied
+    /// returns the number of replayed changes
+    pub fn replay(&self) -> Result<usize> {
+        let file = match File::open(self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut replayed = 0;
+        for line in BufReader::new(file).lines() {
+            let Ok(record) = from_str::<Record>(&line?) else {
+                break;
+            };
+            write_entry(&record.project, record.entry.as_ref())?;
+            replayed += 1;
+        }
+        self.checkpoint()?;
+        Ok(replayed)
+    }
+
+    /// flushes the entry files to the disk and empties the journal
+    /// waits for the changes being applied so no recorded change is lost
+    /// nothing is flushed when nothing was recorded since the last checkpoint
+    pub fn checkpoint(&self) -> Result<()> {
+        let _applying = self.applying.write().map_err(|_| anyhow!("journal lock poisoned"))?;
+        if self.size() == 0 {
+            return Ok(());
+        }
+        files::sync()?;
+
+        let mut file = self.file.lock().map_err(|_| anyhow!("journal lock poisoned"))?;
+        // reopened on the next record
+        *file = None;
+        match OpenOptions::new().write(true).open(self.path) {
+            Ok(file) => {
+                file.set_len(0)?;
+                file.sync_all()?;
+            },
+            Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+            _ => {},
+        }
+        Ok(())
+    }
+
+    /// size of the journal in bytes
+    pub fn size(&self) -> u64 {
+        std::fs::metadata(self.path).map(|m| m.len()).unwrap_or(0)
+    }
+}
+
+/// writing the entry file directly, None removes the entry
+/// err indicates fs or json error
+fn write_entry(project_name: &str, entry: Option<&ProjectEntry>) -> Result<()> {
+    let path = format!("data/{}", project_name);
+    match entry {
+        Some(entry) => {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(path)?;
+            file.write_all(&to_vec(entry)?)?;
+        },
+        None => {
+            if let Err(err) = remove_file(path) {
+                if err.kind() != ErrorKind::NotFound {
+                    return Err(err.into());
+                }
+            }
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: u128) -> ProjectEntry {
+        ProjectEntry {
+            timestamp,
+            vars: Vec::new(),
+            path: "./.env".to_string(),
+            schema: None,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn replaying() {
+        let journal = Journal::new("data/.journal-test");
+        _ = std::fs::remove_file("data/journal-test-a");
+        _ = std::fs::remove_file("data/journal-test-b");
+
+        // changes recorded but never applied, as if the server crashed
+        drop(journal.record("journal-test-a", Some(&entry(1))).unwrap());
+        drop(journal.record("journal-test-b", Some(&entry(1))).unwrap());
+        drop(journal.record("journal-test-a", Some(&entry(2))).unwrap());
+        drop(journal.record("journal-test-b", None).unwrap());
+        let mut file = OpenOptions::new().append(true).open("data/.journal-test").unwrap();
+        file.write_all(b"{\"project\":\"journal-test-b\",\"ent").unwrap();
+
+        assert_eq!(4, journal.replay().unwrap());
+        assert_eq!(Some(entry(2)), files::read("journal-test-a").await.unwrap());
+        assert_eq!(None, files::read("journal-test-b").await.unwrap());
+
+        // checkpoint emptied the journal
+        assert_eq!(0, journal.size());
+        assert_eq!(0, journal.replay().unwrap());
+
+        files::delete("journal-test-a").await.unwrap();
+        _ = std::fs::remove_file("data/.journal-test");
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant}
+};
+use actix_web::{dev::ServiceRequest, http::Method};
+use crate::handlers::AdminToken;
+
+/// number of clients after which the ones with a full bucket are forgotten
+const PRUNE_AT: usize = 1024;
+
+/// tokens left to a client and when they were last counted
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// token bucket per client, full bucket holds a minute worth of requests
+pub struct RateLimiter {
+    /// requests allowed per minute, 0 turns the limit off
+    per_minute: u32,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        RateLimiter {
+            per_minute,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// takes a token of the client, err holds how long until the next token
+    pub fn check(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        if self.per_minute == 0 {
+            return Ok(());
+        }
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return Ok(());
+        };
+        let capacity = self.per_minute as f64;
+        let per_second = capacity / 60.0;
+
+        if buckets.len() >= PRUNE_AT {
+            buckets.retain(|_, b| b.tokens + now.duration_since(b.updated).as_secs_f64() * per_second < capacity);
+        }
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        bucket.tokens = (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * per_second).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens < 1.0 {
+            return Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second));
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+/// separate limits for reads and writes so a client flooding writes can still read
+pub struct RateLimits {
+    reads: RateLimiter,
+    writes: RateLimiter,
+}
+
+impl RateLimits {
+    pub fn new(reads_per_minute: u32, writes_per_minute: u32) -> Self {
+        RateLimits {
+            reads: RateLimiter::new(reads_per_minute),
+            writes: RateLimiter::new(writes_per_minute),
+        }
+    }
+
+    /// takes a token of the client sending the request, err holds how long until the next token
+    /// probes, metrics and requests with the admin token, like the ones of replicas, are never limited
+    pub fn check(&self, req: &ServiceRequest, admin: &AdminToken) -> Result<(), Duration> {
+        if matches!(req.path(), "/healthz" | "/readyz" | "/metrics") || admin.authorized(req.headers()) {
+            return Ok(());
+        }
+        let limiter = match *req.method() {
+            Method::GET | Method::HEAD => &self.reads,
+            _ => &self.writes,
+        };
+        limiter.check(&client_key(req), Instant::now())
+    }
+}
+
+/// clients are told apart by their address
+/// tokens aren't checked by the server, keying by them would give a fresh bucket to every made up token
+fn client_key(req: &ServiceRequest) -> String {
+    req.peer_addr()
+        .map(|a| a.ip().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limiting() {
+        let limiter = RateLimiter::new(60);
+        let now = Instant::now();
+        for _ in 0..60 {
+            assert_eq!(Ok(()), limiter.check("a", now));
+        }
+        // bucket is empty, a token comes back every second
+        assert_eq!(Err(Duration::from_secs(1)), limiter.check("a", now));
+        assert_eq!(Ok(()), limiter.check("b", now));
+        assert_eq!(Ok(()), limiter.check("a", now + Duration::from_secs(1)));
+        assert!(limiter.check("a", now + Duration::from_secs(1)).is_err());
+
+        let limiter = RateLimiter::new(0);
+        for _ in 0..100 {
+            assert_eq!(Ok(()), limiter.check("a", now));
+        }
+    }
+
+    #[test]
+    fn rotating_tokens() {
+        let limits = RateLimits::new(2, 2);
+        let admin = AdminToken::new(Some("secret".to_string()));
+        let addr = "10.0.0.1:4000".parse().unwrap();
+        for i in 0..3 {
+            let req = actix_web::test::TestRequest::get()
+                .uri("/v1/projects/a")
+                .peer_addr(addr)
+                .insert_header(("Authorization", format!("Bearer token-{}", i)))
+                .to_srv_request();
+            assert_eq!(i < 2, limits.check(&req, &admin).is_ok());
+        }
+
+        // admin token, sent by replicas, is not limited
+        let req = actix_web::test::TestRequest::get()
+            .uri("/v1/admin/backup")
+            .peer_addr(addr)
+            .insert_header(("Authorization", "Bearer secret"))
+            .to_srv_request();
+        assert_eq!(Ok(()), limits.check(&req, &admin));
+    }
+}
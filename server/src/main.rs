@@ -1,18 +1,61 @@
-use std::{sync::Arc, thread};
+use std::{
+    sync::{Arc, mpsc as std_mpsc, mpsc::RecvTimeoutError},
+    thread,
+    time::{Duration, Instant}
+};
 use actix_web::{
-    App, HttpServer, web, error, HttpResponse, middleware::Logger,
-    web::Data,
+    App, HttpServer, web, error, HttpResponse, middleware::{Logger, Compress},
+    web::Data, dev::Service,
 };
 use env_logger::Env;
-use tokio::runtime::Builder;
+use futures_util::future::{Either, ready};
+use senvy_common::error::{ApiError, ErrorCode};
+use tokio::{runtime::Builder, signal, sync::mpsc};
 
+pub mod cache;
+pub mod events;
 pub mod files;
 pub mod handlers;
+pub mod journal;
+pub mod limit;
+pub mod metrics;
+pub mod offline;
 pub mod queue;
+pub mod replica;
 
 const LOGGER_FORMAT: &str = "[%t] %a %s UA:%{User-Agent}i CT:%{Content-Type}i %Dms";
+/// default number of threads executing file tasks, they mostly wait on the disk
+const WORKERS: usize = 4;
+/// default max number of tasks waiting in the queue
+const QUEUE_SIZE: usize = 1024;
+/// default time in seconds a request waits for its task
+const TASK_TIMEOUT: u64 = 10;
+/// default time in seconds for finishing requests and then queued tasks on shutdown
+const SHUTDOWN_TIMEOUT: u64 = 30;
+/// default time in seconds between checkpoints of the journal
+const CHECKPOINT_INTERVAL: u64 = 60;
+/// default number of reads a client can send per minute
+const READ_RATE_LIMIT: u32 = 600;
+/// default number of writes a client can send per minute
+const WRITE_RATE_LIMIT: u32 = 120;
+/// default number of entries kept in memory
+const CACHE_SIZE: usize = 1024;
+/// default body limit of a single project
+const MAX_BODY_SIZE: usize = 64 * 1024;
+/// batch can hold many projects so its limit is a multiple of the single project limit
+const BATCH_BODY_FACTOR: usize = 64;
 
 fn main() {
+    // backup and restore of the data directory while the server is stopped
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(err) = offline::run(&args) {
+            eprintln!("Error: {:#}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let port = std::env::var("PORT");
     let port = match port {
         Ok(p) => {
@@ -28,7 +71,143 @@ fn main() {
         }
     };
 
-    // always give one core to the worker and the rest to the server
+    // limit applies to the decompressed body
+    let max_body_size = std::env::var("MAX_BODY_SIZE");
+    let max_body_size = match max_body_size {
+        Ok(s) => {
+            let s = s.parse::<usize>();
+            if s.is_err() {
+                panic!("Malformed max body size env var");
+            }
+            s.unwrap()
+        },
+        Err(_) => MAX_BODY_SIZE,
+    };
+
+    // tasks of different projects are executed in parallel by the workers
+    let workers = std::env::var("WORKERS");
+    let workers = match workers {
+        Ok(w) => {
+            let w = w.parse::<usize>();
+            if w.is_err() || w == Ok(0) {
+                panic!("Malformed workers env var");
+            }
+            w.unwrap()
+        },
+        Err(_) => WORKERS,
+    };
+
+    // 0 turns the cache off
+    let cache_size = std::env::var("CACHE_SIZE");
+    let cache_size = match cache_size {
+        Ok(c) => {
+            let c = c.parse::<usize>();
+            if c.is_err() {
+                panic!("Malformed cache size env var");
+            }
+            c.unwrap()
+        },
+        Err(_) => CACHE_SIZE,
+    };
+
+    // requests wait for space in a full queue until the task timeout
+    let queue_size = std::env::var("QUEUE_SIZE");
+    let queue_size = match queue_size {
+        Ok(q) => {
+            let q = q.parse::<usize>();
+            if q.is_err() || q == Ok(0) {
+                panic!("Malformed queue size env var");
+            }
+            q.unwrap()
+        },
+        Err(_) => QUEUE_SIZE,
+    };
+    let task_timeout = std::env::var("TASK_TIMEOUT");
+    let task_timeout = match task_timeout {
+        Ok(t) => {
+            let t = t.parse::<u64>();
+            if t.is_err() || t == Ok(0) {
+                panic!("Malformed task timeout env var");
+            }
+            t.unwrap()
+        },
+        Err(_) => TASK_TIMEOUT,
+    };
+
+    let shutdown_timeout = std::env::var("SHUTDOWN_TIMEOUT");
+    let shutdown_timeout = match shutdown_timeout {
+        Ok(t) => {
+            let t = t.parse::<u64>();
+            if t.is_err() {
+                panic!("Malformed shutdown timeout env var");
+            }
+            t.unwrap()
+        },
+        Err(_) => SHUTDOWN_TIMEOUT,
+    };
+
+    // journal is emptied on every checkpoint so this bounds the work of replaying it
+    let checkpoint_interval = std::env::var("CHECKPOINT_INTERVAL");
+    let checkpoint_interval = match checkpoint_interval {
+        Ok(c) => {
+            let c = c.parse::<u64>();
+            if c.is_err() || c == Ok(0) {
+                panic!("Malformed checkpoint interval env var");
+            }
+            c.unwrap()
+        },
+        Err(_) => CHECKPOINT_INTERVAL,
+    };
+
+    // requests per minute of a single client, 0 turns the limit off
+    let read_rate_limit = std::env::var("READ_RATE_LIMIT");
+    let read_rate_limit = match read_rate_limit {
+        Ok(r) => {
+            let r = r.parse::<u32>();
+            if r.is_err() {
+                panic!("Malformed read rate limit env var");
+            }
+            r.unwrap()
+        },
+        Err(_) => READ_RATE_LIMIT,
+    };
+    let write_rate_limit = std::env::var("WRITE_RATE_LIMIT");
+    let write_rate_limit = match write_rate_limit {
+        Ok(w) => {
+            let w = w.parse::<u32>();
+            if w.is_err() {
+                panic!("Malformed write rate limit env var");
+            }
+            w.unwrap()
+        },
+        Err(_) => WRITE_RATE_LIMIT,
+    };
+
+    // server with a primary is a read-only replica following the changes of the primary
+    let primary = std::env::var("PRIMARY_URL");
+    let primary = match primary {
+        Ok(p) => {
+            let p = url::Url::parse(&p);
+            if p.is_err() {
+                panic!("Malformed primary url env var");
+            }
+            Some(p.unwrap())
+        },
+        Err(_) => None,
+    };
+
+    // admin token of the primary, replica can't read the backup of the primary without it
+    let primary = primary.map(|primary| {
+        match std::env::var("PRIMARY_TOKEN") {
+            Ok(token) if !token.is_empty() => (primary, token),
+            _ => panic!("Primary token env var is required when primary url env var is set"),
+        }
+    });
+
+    // admin endpoints are disabled unless a token is set
+    let admin_token = Data::new(handlers::AdminToken::new(std::env::var("ADMIN_TOKEN").ok()));
+
+    // always give one core to the workers and the rest to the server
     let cpus = num_cpus::get();
     let cpus = match cpus {
         1 => 1, // in case there is only one core, worker and server are going to share it
@@ -38,49 +217,168 @@ fn main() {
 
     env_logger::init_from_env(Env::default().default_filter_or("info"));
 
-    let json_config = web::JsonConfig::default()
-        .limit(4096)
-        .error_handler(|_, _| {
-            error::InternalError::from_response("", HttpResponse::BadRequest().body("malformed json")).into()
-        });
+    // changes cut off by a crash are applied before anything reads the entries
+    match journal::journal().replay() {
+        Ok(0) => {},
+        Ok(n) => log::info!("Replayed {} changes from the journal", n),
+        Err(err) => panic!("Error replaying the journal: {}", err),
+    }
+
+    let job_queue = Arc::new(queue::FileTaskQueue::new(queue_size, Duration::from_secs(task_timeout), primary.is_some()));
+    let events = events::Events::new();
+    let metrics = Arc::new(metrics::Metrics::new());
 
-    let job_queue = Arc::new(queue::FileTaskQueue::new());
+    // projects of unfinished tasks are logged if they don't finish before the shutdown deadline
+    let chain = queue::KeyChain::new();
 
-    // worker thread
+    // worker thread, reports on the channel once every task finished
+    let (drained_tx, drained_rx) = std_mpsc::channel::<()>();
     let job_queue_worker = job_queue.clone();
+    let events_worker = events.clone();
+    let metrics_worker = metrics.clone();
+    let chain_worker = chain.clone();
     let worker_thread = thread::spawn(move || {
         let job_queue = job_queue_worker;
+        let events = events_worker;
+        let metrics = metrics_worker;
+        let chain = chain_worker;
+        let _alive = metrics::WorkerAlive::new(metrics.clone());
+        let cache = Arc::new(cache::EntryCache::new(cache_size, metrics.clone()));
 
-        // tokio runtime for the current thread beacuse the queue it self is async
-        let worker_runtime = Builder::new_current_thread()
-            .worker_threads(1)
-            .enable_io()
+        let worker_runtime = Builder::new_multi_thread()
+            .worker_threads(workers)
+            .enable_all()
+            .thread_name("worker")
             .build()
             .unwrap();
 
-        let worker_runtime_handle = worker_runtime.handle();
-        let worker = worker_runtime_handle.spawn(async move {
-            let job_queue = job_queue;
-            while let Some(mut task) = job_queue.wait_for_task() {
-                (&mut task).execute().await;
+        // tasks are taken out on this thread and chained in the order they were pushed
+        // so tasks of the same project keep their order while the rest run in parallel
+        worker_runtime.block_on(async move {
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(checkpoint_interval));
+                loop {
+                    interval.tick().await;
+                    let res = tokio::task::spawn_blocking(|| journal::journal().checkpoint()).await;
+                    if let Ok(Err(err)) = res {
+                        log::error!("Error checkpointing the journal: {}", err);
+                    }
+                }
+            });
+
+            // every running task holds a sender, recv returns None once all of them finished
+            let (running, mut finished) = mpsc::channel::<()>(1);
+            let mut reads = queue::ReadGroups::new();
+            while let Some(task) = job_queue.wait_for_task().await {
+                // joined reads are done as far as the queue is concerned
+                let Some(mut task) = reads.join(task) else {
+                    metrics.observe_coalesced_read();
+                    job_queue.task_done();
+                    continue;
+                };
+                let mut turn = match task.exclusive() {
+                    true => chain.enter_all(),
+                    false => chain.enter(task.projects()),
+                };
+                let job_queue = job_queue.clone();
+                let events = events.clone();
+                let metrics = metrics.clone();
+                let cache = cache.clone();
+                let running = running.clone();
+                tokio::spawn(async move {
+                    turn.ready().await;
+                    // nobody is waiting for the result of a read anymore, the client already got a timeout
+                    // writes are still applied since the client was told that they might be
+                    if !(task.abandoned() && task.read_only()) {
+                        let start = Instant::now();
+                        let kind = task.kind();
+                        task.execute(&events, &cache).await;
+                        metrics.observe_task(kind, start.elapsed());
+                    }
+                    job_queue.task_done();
+                    drop(turn);
+                    drop(running);
+                });
             }
+            drop(running);
+            _ = finished.recv().await;
         });
-        _ = worker_runtime.block_on(worker);
+        _ = drained_tx.send(());
     });
 
     let job_queue_server = job_queue.clone();
+    let events_server = events.clone();
+    let metrics_server = metrics.clone();
+    // shared by the workers of the server so a client can't spread requests over them
+    let limits = Arc::new(limit::RateLimits::new(read_rate_limit, write_rate_limit));
     let server = HttpServer::new(move || {
         let job_queue = job_queue_server.clone();
+        let metrics = metrics_server.clone();
+        let limits = limits.clone();
+        let admin = admin_token.clone();
         App::new()
+            // limited requests are rejected before reaching the queue
+            .wrap_fn(move |req, srv| match limits.check(&req, &admin) {
+                Ok(()) => Either::Left(srv.call(req)),
+                Err(wait) => Either::Right(ready(Ok(req.into_response(handlers::rate_limited_response(wait))))),
+            })
+            // requests are labeled with the matched route pattern so project names don't end up in metrics
+            .wrap_fn(move |req, srv| {
+                let start = Instant::now();
+                let method = req.method().to_string();
+                let metrics = metrics.clone();
+                let res = srv.call(req);
+                async move {
+                    let res = res.await;
+                    let (handler, status) = match &res {
+                        Ok(res) => (res.request().match_pattern(), res.status()),
+                        Err(err) => (None, err.as_response_error().status_code()),
+                    };
+                    let handler = handler.unwrap_or_else(|| "unmatched".to_string());
+                    metrics.observe_request(&handler, &method, status.as_u16(), start.elapsed());
+                    res
+                }
+            })
             .wrap(Logger::new(LOGGER_FORMAT))
-            .app_data(json_config.clone())
+            // gzip bodies of requests are decompressed by the json extractor
+            .wrap(Compress::default())
+            .app_data(json_config(max_body_size))
             .app_data(Data::new(Arc::clone(&job_queue)))
-            .service(handlers::new)
-            .service(handlers::read)
-            .service(handlers::update)
-            .service(handlers::delete)
-            .service(handlers::exists)
-    }).bind(("127.0.0.1", port));
+            .app_data(Data::new(events_server.clone()))
+            .app_data(Data::new(metrics_server.clone()))
+            .app_data(admin_token.clone())
+            .service(handlers::health::healthz)
+            .service(handlers::health::readyz)
+            .service(handlers::health::prometheus)
+            .service(web::scope("/v1")
+                .service(handlers::v1::read)
+                .service(handlers::v1::exists)
+                .service(handlers::v1::put)
+                .service(handlers::v1::patch)
+                .service(handlers::v1::delete)
+                .service(handlers::v1::put_schema)
+                .service(handlers::v1::delete_schema)
+                .service(handlers::v1::rename)
+                .service(handlers::v1::copy)
+                .service(web::resource("/batch")
+                    .app_data(json_config(max_body_size.saturating_mul(BATCH_BODY_FACTOR)))
+                    .route(web::post().to(handlers::v1::batch)))
+                .service(handlers::v1::backup)
+                .service(web::resource("/admin/restore")
+                    .app_data(json_config(max_body_size.saturating_mul(BATCH_BODY_FACTOR)))
+                    .route(web::post().to(handlers::v1::restore)))
+                .service(handlers::v1::events))
+            // legacy endpoints taking the project name from the body, kept for older clis
+            .service(handlers::legacy::new)
+            .service(handlers::legacy::read)
+            .service(handlers::legacy::update)
+            .service(handlers::legacy::delete)
+            .service(handlers::legacy::exists)
+            .service(handlers::legacy::schema)
+    })
+    .disable_signals()
+    .shutdown_timeout(shutdown_timeout)
+    .bind(("127.0.0.1", port));
     if server.is_err() {
         println!("Error binding to port {}: {}\n", port, server.err().unwrap());
         return;
@@ -96,12 +394,97 @@ fn main() {
         .build()
         .unwrap();
 
+    // signals are handled here so open event streams can be closed before actix waits for them
     let server_future = server.run();
-    _ = actix_runtime.block_on(server_future);
+    let server_handle = server_future.handle();
+    let replica = primary.map(|(primary, token)| {
+        log::info!("Running as a read-only replica of {}", primary);
+        replica::Replica::new(primary, token, job_queue.clone()).unwrap()
+    });
+    // shutdown timeout counts from the signal, requests and queued tasks share it
+    let (signaled_tx, signaled_rx) = std_mpsc::channel::<Instant>();
+    _ = actix_runtime.block_on(async move {
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            _ = signaled_tx.send(Instant::now());
+            events.close();
+            server_handle.stop(true).await;
+        });
+        let follower = replica.map(|replica| tokio::spawn(async move { replica.follow().await }));
+        let res = server_future.await;
+        // changes from the primary stop with the server so the queue can be drained
+        if let Some(follower) = follower {
+            follower.abort();
+        }
+        res
+    });
 
-    // actix task will finish either by erroring or by being interrupted and at that point its safe
-    // to kill the worker thread
-    // gracefully killing worker thread via queue and waiting for worker thread to finish
+    // actix task will finish either by erroring or by being interrupted and at that point no new
+    // tasks can come in, tasks already in the queue are finished before the worker thread ends
     job_queue.end();
-    _ = worker_thread.join();
+    // server stopped without a signal only on an error, queued tasks get the whole timeout then
+    let signaled = signaled_rx.try_recv().unwrap_or_else(|_| Instant::now());
+    let left = (signaled + Duration::from_secs(shutdown_timeout)).saturating_duration_since(Instant::now());
+    let drained = match drained_rx.recv_timeout(left) {
+        // disconnected means that the worker thread panicked, there is nothing left to wait for
+        Ok(()) | Err(RecvTimeoutError::Disconnected) => {
+            _ = worker_thread.join();
+            true
+        },
+        Err(RecvTimeoutError::Timeout) => {
+            let mut projects = chain.keys();
+            projects.sort();
+            log::warn!("Shutdown deadline passed, abandoning {} unfinished tasks of projects: {}",
+                job_queue.depth(), projects.join(", "));
+            false
+        },
+    };
+
+    // entries written by finished tasks are on the disk before exiting and the journal is empty
+    // abandoned tasks might still be writing, their changes are left in the journal for the next start
+    if !drained {
+        log::warn!("Server stopped without a clean shutdown, the journal is replayed on the next start");
+        return;
+    }
+    if let Err(err) = journal::journal().checkpoint() {
+        log::error!("Error checkpointing the journal: {}", err);
+    }
+    log::info!("Server stopped");
+}
+
+/// json extractor config, errors are returned as malformed json or payload too large
+fn json_config(limit: usize) -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(limit)
+        .error_handler(move |err, _| {
+            let res = match err {
+                error::JsonPayloadError::OverflowKnownLength{..} | error::JsonPayloadError::Overflow{..} => {
+                    let message = format!("request body is larger than the limit of {} bytes", limit);
+                    HttpResponse::PayloadTooLarge().json(ApiError::new(ErrorCode::PayloadTooLarge, &message))
+                },
+                _ => {
+                    let message = format!("malformed json: {}", err);
+                    HttpResponse::BadRequest().json(ApiError::new(ErrorCode::MalformedJson, &message))
+                },
+            };
+            error::InternalError::from_response(err, res).into()
+        })
+}
+
+/// resolves on ctrl-c or SIGTERM
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("registering SIGTERM handler");
+        tokio::select! {
+            _ = signal::ctrl_c() => {},
+            _ = terminate.recv() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        _ = signal::ctrl_c().await;
+    }
+    log::info!("Shutdown signal received, stopping the server");
 }
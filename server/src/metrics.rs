@@ -0,0 +1,198 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}},
+    time::Duration
+};
+
+/// upper bounds of histogram buckets in seconds
+const BUCKETS: [f64; 11] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+/// counts of observations in cumulative buckets
+#[derive(Default, Clone)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS.iter()) {
+            if secs <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += secs;
+    }
+
+    /// appends the histogram in prometheus text format, labels are without braces
+    fn render(&self, buff: &mut String, name: &str, labels: &str) {
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS.iter()) {
+            _ = writeln!(buff, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, bound, bucket);
+        }
+        _ = writeln!(buff, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, self.count);
+        _ = writeln!(buff, "{}_sum{{{}}} {}", name, labels, self.sum);
+        _ = writeln!(buff, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+/// request and worker statistics exposed in prometheus text format
+#[derive(Default)]
+pub struct Metrics {
+    /// handler pattern, method, status -> number of requests
+    requests: Mutex<BTreeMap<(String, String, u16), u64>>,
+    request_durations: Mutex<BTreeMap<String, Histogram>>,
+    task_durations: Mutex<BTreeMap<&'static str, Histogram>>,
+    worker_alive: AtomicBool,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    cache_entries: AtomicUsize,
+    coalesced_reads: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe_request(&self, handler: &str, method: &str, status: u16, duration: Duration) {
+        if let Ok(mut requests) = self.requests.lock() {
+            *requests.entry((handler.to_string(), method.to_string(), status)).or_insert(0) += 1;
+        }
+        if let Ok(mut durations) = self.request_durations.lock() {
+            durations.entry(handler.to_string()).or_default().observe(duration);
+        }
+    }
+
+    pub fn observe_task(&self, task: &'static str, duration: Duration) {
+        if let Ok(mut durations) = self.task_durations.lock() {
+            durations.entry(task).or_default().observe(duration);
+        }
+    }
+
+    pub fn observe_cache(&self, hit: bool) {
+        match hit {
+            true => self.cache_hits.fetch_add(1, Ordering::Relaxed),
+            false => self.cache_misses.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    pub fn observe_coalesced_read(&self) {
+        self.coalesced_reads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_cache_entries(&self, entries: usize) {
+        self.cache_entries.store(entries, Ordering::Relaxed);
+    }
+
+    pub fn worker_alive(&self) -> bool {
+        self.worker_alive.load(Ordering::SeqCst)
+    }
+
+    /// renders every metric, queue depth is passed in since the queue is owned elsewhere
+    pub fn render(&self, queue_depth: usize) -> String {
+        let mut buff = String::new();
+
+        buff += "# HELP senvy_http_requests_total Number of handled requests.\n";
+        buff += "# TYPE senvy_http_requests_total counter\n";
+        if let Ok(requests) = self.requests.lock() {
+            for ((handler, method, status), count) in requests.iter() {
+                _ = writeln!(buff, "senvy_http_requests_total{{handler=\"{}\",method=\"{}\",status=\"{}\"}} {}",
+                    escape(handler), method, status, count);
+            }
+        }
+
+        buff += "# HELP senvy_http_request_duration_seconds Time spent handling requests.\n";
+        buff += "# TYPE senvy_http_request_duration_seconds histogram\n";
+        if let Ok(durations) = self.request_durations.lock() {
+            for (handler, histogram) in durations.iter() {
+                let labels = format!("handler=\"{}\"", escape(handler));
+                histogram.render(&mut buff, "senvy_http_request_duration_seconds", &labels);
+            }
+        }
+
+        buff += "# HELP senvy_task_duration_seconds Time spent executing file tasks in the worker.\n";
+        buff += "# TYPE senvy_task_duration_seconds histogram\n";
+        if let Ok(durations) = self.task_durations.lock() {
+            for (task, histogram) in durations.iter() {
+                let labels = format!("task=\"{}\"", task);
+                histogram.render(&mut buff, "senvy_task_duration_seconds", &labels);
+            }
+        }
+
+        buff += "# HELP senvy_queue_depth Number of tasks waiting for or in execution by the workers.\n";
+        buff += "# TYPE senvy_queue_depth gauge\n";
+        _ = writeln!(buff, "senvy_queue_depth {}", queue_depth);
+
+        buff += "# HELP senvy_cache_hits_total Number of entry reads served from memory.\n";
+        buff += "# TYPE senvy_cache_hits_total counter\n";
+        _ = writeln!(buff, "senvy_cache_hits_total {}", self.cache_hits.load(Ordering::Relaxed));
+
+        buff += "# HELP senvy_cache_misses_total Number of entry reads that went to the disk.\n";
+        buff += "# TYPE senvy_cache_misses_total counter\n";
+        _ = writeln!(buff, "senvy_cache_misses_total {}", self.cache_misses.load(Ordering::Relaxed));
+
+        buff += "# HELP senvy_cache_entries Number of entries kept in memory.\n";
+        buff += "# TYPE senvy_cache_entries gauge\n";
+        _ = writeln!(buff, "senvy_cache_entries {}", self.cache_entries.load(Ordering::Relaxed));
+
+        buff += "# HELP senvy_coalesced_reads_total Number of reads that got the result of the same read before them.\n";
+        buff += "# TYPE senvy_coalesced_reads_total counter\n";
+        _ = writeln!(buff, "senvy_coalesced_reads_total {}", self.coalesced_reads.load(Ordering::Relaxed));
+
+        buff += "# HELP senvy_worker_up Whether the thread running the workers is up.\n";
+        buff += "# TYPE senvy_worker_up gauge\n";
+        _ = writeln!(buff, "senvy_worker_up {}", self.worker_alive() as u8);
+
+        buff
+    }
+}
+
+/// marks the worker as alive while it exists, dropped also when the worker panics
+pub struct WorkerAlive(Arc<Metrics>);
+
+impl WorkerAlive {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        metrics.worker_alive.store(true, Ordering::SeqCst);
+        WorkerAlive(metrics)
+    }
+}
+
+impl Drop for WorkerAlive {
+    fn drop(&mut self) {
+        self.0.worker_alive.store(false, Ordering::SeqCst);
+    }
+}
+
+/// label values can't contain raw quotes, backslashes or new lines
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let metrics = Arc::new(Metrics::new());
+        metrics.observe_request("/v1/projects/{name}", "GET", 200, Duration::from_millis(2));
+        metrics.observe_request("/v1/projects/{name}", "GET", 200, Duration::from_millis(20));
+        metrics.observe_task("read", Duration::from_millis(3));
+
+        let alive = WorkerAlive::new(metrics.clone());
+        let res = metrics.render(4);
+        assert!(res.contains("senvy_http_requests_total{handler=\"/v1/projects/{name}\",method=\"GET\",status=\"200\"} 2\n"));
+        assert!(res.contains("senvy_http_request_duration_seconds_bucket{handler=\"/v1/projects/{name}\",le=\"0.005\"} 1\n"));
+        assert!(res.contains("senvy_http_request_duration_seconds_bucket{handler=\"/v1/projects/{name}\",le=\"+Inf\"} 2\n"));
+        assert!(res.contains("senvy_task_duration_seconds_count{task=\"read\"} 1\n"));
+        assert!(res.contains("senvy_queue_depth 4\n"));
+        assert!(res.contains("senvy_worker_up 1\n"));
+
+        drop(alive);
+        assert!(!metrics.worker_alive());
+    }
+}
@@ -0,0 +1,66 @@
+use std::fs::{create_dir_all, read_to_string, write};
+use anyhow::{Result, Context, anyhow};
+use senvy_common::types::{Archive, ConflictPolicy};
+use crate::{
+    files::{self, RestoreResult},
+    handlers::{timestamp, valid_name},
+    journal::journal
+};
+
+const USAGE: &str = "usage: senvy [backup <archive file> | restore <archive file> [--policy fail|skip|overwrite]]";
+
+/// backs up or restores the data directory of a stopped server, the server must not run meanwhile
+pub fn run(args: &[String]) -> Result<()> {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .context("building runtime")?;
+    match args {
+        [command, file] if command == "backup" => rt.block_on(backup(file)),
+        [command, file] if command == "restore" => rt.block_on(restore(file, ConflictPolicy::Fail)),
+        [command, file, flag, policy] if command == "restore" && flag == "--policy" => {
+            let policy = serde_json::from_value(serde_json::Value::String(policy.clone()))
+                .map_err(|_| anyhow!("policy has to be fail, skip or overwrite"))?;
+            rt.block_on(restore(file, policy))
+        },
+        _ => Err(anyhow!(USAGE)),
+    }
+}
+
+async fn backup(file: &str) -> Result<()> {
+    // changes cut off by a crash are part of the data set
+    journal().replay().context("replaying the journal")?;
+    let archive = files::backup(timestamp()).await
+        .context("reading entries")?;
+    write(file, serde_json::to_string(&archive)?)
+        .context("writing archive file")?;
+    println!("Saved {} projects to {}", archive.entries.len(), file);
+    Ok(())
+}
+
+async fn restore(file: &str, policy: ConflictPolicy) -> Result<()> {
+    let archive: Archive = serde_json::from_str(&read_to_string(file).context("reading archive file")?)
+        .context("parsing archive")?;
+    if let Some(project_name) = archive.entries.keys().find(|n| !valid_name(n)) {
+        return Err(anyhow!("invalid project name in archive: {}", project_name));
+    }
+
+    // restoring into an empty data directory of a new server
+    create_dir_all("data").context("creating data directory")?;
+    journal().replay().context("replaying the journal")?;
+    let res = files::restore(&archive, policy).await
+        .context("restoring entries")?;
+    journal().checkpoint().context("checkpointing the journal")?;
+    match res {
+        RestoreResult::Done(res) => {
+            println!("Restored {} projects, skipped {}", res.restored.len(), res.skipped.len());
+            Ok(())
+        },
+        RestoreResult::Conflicts(existing) => Err(anyhow!("projects already exist: {}", existing.join(", "))),
+        RestoreResult::Invalid(project_name, violations) => {
+            let violations: Vec<String> = violations.iter()
+                .map(|v| format!("{}: {}", v.var, v.message))
+                .collect();
+            Err(anyhow!("vars of {} do not follow the schema: {}", project_name, violations.join(", ")))
+        },
+    }
+}
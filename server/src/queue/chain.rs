@@ -0,0 +1,180 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}}
+};
+use tokio::sync::watch;
+
+/// id of the task and a receiver closed when it finishes
+type Link = (u64, watch::Receiver<()>);
+
+/// last tasks that following tasks wait for
+#[derive(Default)]
+struct Links {
+    /// project name -> last task touching it
+    keys: HashMap<String, Link>,
+    /// last task touching every key, every following task waits for it
+    all: Option<Link>,
+}
+
+/// orders tasks touching the same projects in the order they entered
+/// tasks on different projects don't wait for each other
+#[derive(Clone, Default)]
+pub struct KeyChain {
+    last: Arc<Mutex<Links>>,
+    next_id: Arc<AtomicU64>,
+}
+
+/// place of a task in the chain, dropping it lets the following tasks run
+pub struct Turn {
+    id: u64,
+    keys: Vec<String>,
+    /// task entered on every key
+    all: bool,
+    /// previous tasks on the same keys
+    wait: Vec<watch::Receiver<()>>,
+    /// never sent on, dropping it closes the receivers of the following tasks
+    _done: watch::Sender<()>,
+    chain: KeyChain,
+}
+
+impl KeyChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// takes the place after the last task on each key
+    /// has to be called in the order the tasks should run
+    pub fn enter(&self, mut keys: Vec<String>) -> Turn {
+        keys.sort();
+        keys.dedup();
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (done, rx) = watch::channel(());
+        let mut last = self.last.lock().unwrap();
+        let Links { keys: last_keys, all } = &mut *last;
+        let wait = keys.iter()
+            .filter_map(|key| last_keys.insert(key.clone(), (id, rx.clone())))
+            .chain(all.clone())
+            .map(|(_, rx)| rx)
+            .collect();
+
+        Turn {
+            id,
+            keys,
+            all: false,
+            wait,
+            _done: done,
+            chain: self.clone(),
+        }
+    }
+
+    /// takes the place after every task that entered, following tasks wait for this one
+    pub fn enter_all(&self) -> Turn {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (done, rx) = watch::channel(());
+        let mut last = self.last.lock().unwrap();
+        let wait = last.keys.values()
+            .chain(last.all.iter())
+            .map(|(_, rx)| rx.clone())
+            .collect();
+        last.all = Some((id, rx));
+
+        Turn {
+            id,
+            keys: Vec::new(),
+            all: true,
+            wait,
+            _done: done,
+            chain: self.clone(),
+        }
+    }
+
+    /// keys with a task that hasn't finished
+    pub fn keys(&self) -> Vec<String> {
+        self.last.lock()
+            .map(|l| l.keys.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// number of keys with a task that hasn't finished
+    pub fn len(&self) -> usize {
+        self.last.lock().map(|l| l.keys.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Turn {
+    /// resolves once every previous task on the same keys finished
+    pub async fn ready(&mut self) {
+        for rx in self.wait.iter_mut() {
+            // error means that the sender was dropped and the task finished
+            while rx.changed().await.is_ok() {}
+        }
+        self.wait.clear();
+    }
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        // keys are only removed if no other task entered after this one
+        if let Ok(mut last) = self.chain.last.lock() {
+            for key in self.keys.iter() {
+                if last.keys.get(key).map(|(id, _)| *id == self.id).unwrap_or(false) {
+                    last.keys.remove(key);
+                }
+            }
+            if self.all && last.all.as_ref().map(|(id, _)| *id == self.id).unwrap_or(false) {
+                last.all = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    #[actix_rt::test]
+    async fn ordering() {
+        let chain = KeyChain::new();
+        let mut first = chain.enter(vec!["a".to_string()]);
+        let mut second = chain.enter(vec!["a".to_string(), "b".to_string()]);
+        let mut other = chain.enter(vec!["c".to_string()]);
+
+        // different keys don't wait, same keys wait for the previous task
+        assert!(first.ready().now_or_never().is_some());
+        assert!(other.ready().now_or_never().is_some());
+        assert!(second.ready().now_or_never().is_none());
+
+        drop(first);
+        assert!(second.ready().now_or_never().is_some());
+        assert_eq!(3, chain.len());
+
+        drop(second);
+        drop(other);
+        assert!(chain.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn ordering_all() {
+        let chain = KeyChain::new();
+        let first = chain.enter(vec!["a".to_string()]);
+        let mut all = chain.enter_all();
+        let mut after = chain.enter(vec!["b".to_string()]);
+
+        // task on every key waits for everything before it and blocks everything after it
+        assert!(all.ready().now_or_never().is_none());
+        assert!(after.ready().now_or_never().is_none());
+
+        drop(first);
+        assert!(all.ready().now_or_never().is_some());
+        assert!(after.ready().now_or_never().is_none());
+
+        drop(all);
+        assert!(after.ready().now_or_never().is_some());
+    }
+}
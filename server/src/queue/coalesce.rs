@@ -0,0 +1,93 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex}
+};
+use super::{Task, task::Followers};
+
+/// groups that already sent their result are removed once there are this many
+const PRUNE_AT: usize = 1024;
+
+/// reads of the same project waiting in the queue share a single read of the entry
+/// a task writing to the project ends the sharing so reads never miss a write before them
+#[derive(Default)]
+pub struct ReadGroups {
+    /// project name -> followers of the last read of it
+    groups: HashMap<String, Followers>,
+}
+
+impl ReadGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// has to be called in the order the tasks were pushed
+    /// None means that the task joined a previous read and gets its result from it
+    pub fn join(&mut self, mut task: Task) -> Option<Task> {
+        for project_name in task.written_projects() {
+            self.groups.remove(&project_name);
+        }
+        let Some(project_name) = task.shared_read().map(|p| p.to_string()) else {
+            return Some(task);
+        };
+
+        if let Some(group) = self.groups.get(&project_name) {
+            if let Ok(mut followers) = group.lock() {
+                // None means that the previous read already sent its result
+                if let Some(followers) = followers.as_mut() {
+                    if let Some(chan) = task.take_chan() {
+                        followers.push(chan);
+                    }
+                    return None;
+                }
+            }
+        }
+
+        if self.groups.len() >= PRUNE_AT {
+            self.groups.retain(|_, g| g.lock().map(|f| f.is_some()).unwrap_or(false));
+        }
+        let followers = Arc::new(Mutex::new(Some(Vec::new())));
+        self.groups.insert(project_name, followers.clone());
+        task.lead(followers);
+        Some(task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+    use senvy_common::types::Project;
+    use crate::queue::{FileTask, task::FileTaskReturnType};
+
+    fn read_task(name: &str) -> (Task, oneshot::Receiver<FileTaskReturnType>) {
+        let (tx, rx) = oneshot::channel();
+        (Task::new(FileTask::ReadConfig(name.to_string()), tx), rx)
+    }
+
+    #[test]
+    fn joining() {
+        let mut groups = ReadGroups::new();
+        let (first, _first_rx) = read_task("a");
+        let first = groups.join(first);
+        assert!(first.is_some());
+
+        // same project joins, other project doesn't
+        let (second, _second_rx) = read_task("a");
+        assert!(groups.join(second).is_none());
+        let (other, _other_rx) = read_task("b");
+        assert!(groups.join(other).is_some());
+
+        // write in between ends the sharing
+        let (tx, _rx) = oneshot::channel();
+        let project = Project {
+            name: "a".to_string(),
+            path: "./.env".to_string(),
+            vars: Vec::new(),
+            author: None,
+            schema: None,
+        };
+        assert!(groups.join(Task::new(FileTask::UpdateConfig(1, project), tx)).is_some());
+        let (third, _third_rx) = read_task("a");
+        assert!(groups.join(third).is_some());
+    }
+}
@@ -1,6 +1,11 @@
+pub mod chain;
+pub mod coalesce;
+#[allow(clippy::module_inception)]
 pub mod queue;
 pub mod task;
 
-pub use queue::FileTaskQueue;
+pub use chain::KeyChain;
+pub use coalesce::ReadGroups;
+pub use queue::{FileTaskQueue, QueueError};
 pub use task::Task;
 pub use task::FileTask;
@@ -1,101 +1,201 @@
-use std::sync::{Mutex, Condvar};
-use std::collections::VecDeque;
-use super::Task;
+use std::{
+    fmt,
+    sync::{Mutex, atomic::{AtomicUsize, Ordering}},
+    time::Duration
+};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::{timeout_at, Instant}
+};
+use super::{Task, FileTask, task::FileTaskReturnType};
 
-/// queue of file tasks to be done on the project entries
-/// tasks are executed sequentially
+/// reason why a task pushed into the queue didn't return a result
+#[derive(Debug, PartialEq)]
+pub enum QueueError {
+    /// queue stayed full for the whole timeout
+    Full,
+    /// queue was ended, server is shutting down
+    Closed,
+    /// task didn't finish before the timeout, it might still be executed
+    TimedOut,
+    /// task was dropped without a result, e.g. the worker panicked
+    Dropped,
+    /// write pushed into the queue of a replica
+    ReadOnly,
+}
+
+impl fmt::Display for QueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            QueueError::Full => "server is busy",
+            QueueError::Closed => "server is shutting down",
+            QueueError::TimedOut => "task timed out",
+            QueueError::Dropped => "task was dropped",
+            QueueError::ReadOnly => "server is a read-only replica, writes go to the primary",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+/// bounded queue of file tasks to be done on the project entries
+/// tasks are taken out in the order they were pushed
 pub struct FileTaskQueue {
-    jobs: Mutex<Option<VecDeque<Task>>>,
-    cvar: Condvar,
+    /// None once the queue is ended
+    sender: Mutex<Option<mpsc::Sender<Task>>>,
+    receiver: tokio::sync::Mutex<mpsc::Receiver<Task>>,
+    /// how long pushing and executing a task can take together
+    task_timeout: Duration,
+    /// tasks pushed and not yet finished
+    pending: AtomicUsize,
+    /// only changes taken from the primary are executed
+    read_only: bool,
 }
 
 impl FileTaskQueue {
-    pub fn new() -> Self {
+    pub fn new(capacity: usize, task_timeout: Duration, read_only: bool) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
         FileTaskQueue {
-            jobs: Mutex::new(Some(VecDeque::new())),
-            cvar: Condvar::new()
+            sender: Mutex::new(Some(sender)),
+            receiver: tokio::sync::Mutex::new(receiver),
+            task_timeout,
+            pending: AtomicUsize::new(0),
+            read_only,
         }
     }
 
-    /// pushes a task into the queue
-    /// return indicates whether the task was successfully pushed or not
-    pub fn push_task(&self, t: Task) -> bool {
-        let jobs = self.jobs.lock();
-        if jobs.is_err() {
-            return false;
-        }
+    /// pushes a task into the queue, waits for space if the queue is full
+    pub async fn push_task(&self, t: Task) -> Result<(), QueueError> {
+        // sender is cloned so the lock isn't held while waiting
+        let sender = self.sender.lock()
+            .map_err(|_| QueueError::Closed)?
+            .clone()
+            .ok_or(QueueError::Closed)?;
+        // space is reserved first so a push dropped while waiting for space is never counted
+        let permit = sender.reserve().await.map_err(|_| QueueError::Closed)?;
+        // counted before sending so the worker can't finish the task before it is counted
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        permit.send(t);
+        Ok(())
+    }
 
-        let mut jobs = jobs.unwrap();
-        if let Some(queue) = jobs.as_mut() {
-            queue.push_back(t);
-            self.cvar.notify_all();
-        } else {
-            return false;
+    /// pushes the task and waits for its result
+    /// the whole wait is limited by the task timeout
+    pub async fn execute(&self, task: FileTask) -> Result<FileTaskReturnType, QueueError> {
+        let (tx, rx) = oneshot::channel();
+        let task = Task::new(task, tx);
+        if self.read_only && !task.read_only() {
+            return Err(QueueError::ReadOnly);
         }
-        true
+        self.wait_result(task, rx).await
+    }
+
+    /// executes a change taken from the primary, the only kind of write a replica executes
+    pub async fn execute_replicated(&self, task: FileTask) -> Result<FileTaskReturnType, QueueError> {
+        let (tx, rx) = oneshot::channel();
+        self.wait_result(Task::new(task, tx), rx).await
+    }
+
+    async fn wait_result(&self, task: Task, rx: oneshot::Receiver<FileTaskReturnType>) -> Result<FileTaskReturnType, QueueError> {
+        let deadline = Instant::now() + self.task_timeout;
+        timeout_at(deadline, self.push_task(task))
+            .await
+            .map_err(|_| QueueError::Full)??;
+        timeout_at(deadline, rx)
+            .await
+            .map_err(|_| QueueError::TimedOut)?
+            .map_err(|_| QueueError::Dropped)
+    }
+
+    /// number of tasks waiting for or in execution
+    pub fn depth(&self) -> usize {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    /// has to be called once for every task taken out of the queue when it finishes
+    pub fn task_done(&self) {
+        _ = self.pending.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |p| p.checked_sub(1));
     }
 
     /// waits for a task to be in queue
-    /// None being returned means that queue has finished and there will be no more tasks
-    /// calling this function after queue finished will result in a panic
-    pub fn wait_for_task(&self) -> Option<Task> {
-        let mut jobs = self.jobs.lock().unwrap();
-        loop {
-            match jobs.as_mut()?.pop_front() {
-                Some(job) => return Some(job),
-                None => {
-                    jobs = self.cvar
-                               .wait(jobs)
-                               .unwrap();
-                }
-            }
-        }
+    /// None being returned means that queue has ended and every task was taken out
+    pub async fn wait_for_task(&self) -> Option<Task> {
+        self.receiver.lock().await.recv().await
     }
 
-    /// ending the queue signals the thread that is consuming the tasks to stop waiting
-    // by dropping the inner queue
+    /// ending the queue stops new tasks from being pushed
+    /// tasks already in the queue are still handed out by wait_for_task
     pub fn end(&self) {
-        let mut jobs = self.jobs.lock().unwrap();
-        *jobs = None;
-        self.cvar.notify_all();
+        if let Ok(mut sender) = self.sender.lock() {
+            *sender = None;
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::queue::FileTask;
-    use tokio::sync::oneshot;
 
     fn new_task() -> Task {
         let (rx, _) = oneshot::channel();
         Task::new(FileTask::ReadConfig("test".to_string()), rx)
     }
 
-    #[test]
-    fn pushing() {
-        let q = FileTaskQueue::new();
+    #[actix_rt::test]
+    async fn pushing() {
+        let q = FileTaskQueue::new(5, Duration::from_secs(1), false);
         for _ in 0..5 {
             let task = new_task();
-            assert_eq!(true, q.push_task(task));
+            assert_eq!(Ok(()), q.push_task(task).await);
         }
+        assert_eq!(5, q.depth());
 
+        // tasks pushed before the end are still handed out
+        q.end();
         let mut number_of_tasks = 0;
-        while q.wait_for_task().is_some() {
+        while q.wait_for_task().await.is_some() {
             number_of_tasks += 1;
-            if number_of_tasks == 5  {
-                q.end();
-            }
+            q.task_done();
         }
-        assert!(true);
+        assert_eq!(5, number_of_tasks);
+        assert_eq!(0, q.depth());
     }
 
-    #[test]
-    fn empty_queue() {
-        let q = FileTaskQueue::new();
+    #[actix_rt::test]
+    async fn ended_queue() {
+        let q = FileTaskQueue::new(1, Duration::from_secs(1), false);
         q.end();
 
         let task = new_task();
-        assert_eq!(false, q.push_task(task));
+        assert_eq!(Err(QueueError::Closed), q.push_task(task).await);
+    }
+
+    #[actix_rt::test]
+    async fn full_queue() {
+        let q = FileTaskQueue::new(1, Duration::from_millis(50), false);
+        assert_eq!(Ok(()), q.push_task(new_task()).await);
+
+        // nothing takes the first task out
+        let res = q.execute(FileTask::ReadConfig("test".to_string())).await;
+        assert_eq!(Some(QueueError::Full), res.err());
+        // only the first task is counted, the rejected one is not
+        assert_eq!(1, q.depth());
+        q.end();
+        while q.wait_for_task().await.is_some() {
+            q.task_done();
+        }
+        assert_eq!(0, q.depth());
+    }
+
+    #[actix_rt::test]
+    async fn read_only_queue() {
+        let q = FileTaskQueue::new(1, Duration::from_millis(50), true);
+        let res = q.execute(FileTask::DeleteConfig("test".to_string())).await;
+        assert_eq!(Some(QueueError::ReadOnly), res.err());
+        assert_eq!(0, q.depth());
+
+        // changes taken from the primary are still pushed
+        let res = q.execute_replicated(FileTask::DeleteConfig("test".to_string())).await;
+        assert_eq!(Some(QueueError::TimedOut), res.err());
+        assert_eq!(1, q.depth());
     }
 }
@@ -1,33 +1,70 @@
-use std::mem::take;
+use std::{mem::take, sync::{Arc, Mutex}};
 use tokio::sync::oneshot::Sender;
-use anyhow::Result;
-use senvy_common::types::{Project, ProjectEntry};
+use anyhow::{Result, anyhow};
+use senvy_common::types::{Archive, Batch, BatchResponse, ConflictPolicy, Project, ProjectEntry, ProjectPatch, ProjectSchema};
 
-use crate::files::{create, read, update, delete};
+use crate::{
+    files::{create, read, version, update, delete, put, patch, set_schema, batch, rename, copy, backup, restore, replicate, WriteResult, RestoreResult},
+    events::Events,
+    cache::EntryCache,
+    handlers::timestamp
+};
 
 /// each file task corresponds to an action on the project entry
 /// every enum variant holds arguments for calling the actions
 pub enum FileTask {
     CreateConfig(u128, Project),
     ReadConfig(String),
+    /// only the version of the entry
+    ExistsConfig(String),
     UpdateConfig(u128, Project),
-    DeleteConfig(String)
+    DeleteConfig(String),
+    SetSchemaConfig(u128, ProjectSchema),
+    PutConfig(u128, Project),
+    PatchConfig(u128, String, ProjectPatch),
+    /// operations on several projects executed as a single task
+    BatchConfig(u128, Batch),
+    /// project name, target name
+    RenameConfig(String, String),
+    /// project name, target name
+    CopyConfig(String, String),
+    /// snapshot of every entry, executed after every task before it and before every task after it
+    BackupConfig(u128),
+    RestoreConfig(Archive, ConflictPolicy),
+    /// entry taken from the primary, None removes it
+    ReplicateConfig(String, Option<ProjectEntry>)
 }
 
 /// return type of each file task
 pub enum FileTaskReturnType{
-    CreateReturn(Result<bool>),
+    CreateReturn(Result<WriteResult>),
     ReadReturn(Result<Option<ProjectEntry>>),
-    UpdateReturn(Result<bool>),
-    DeleteReturn(Result<bool>)
+    ExistsReturn(Result<Option<u128>>),
+    UpdateReturn(Result<WriteResult>),
+    DeleteReturn(Result<bool>),
+    SetSchemaReturn(Result<WriteResult>),
+    PutReturn(Result<(WriteResult, bool)>),
+    PatchReturn(Result<WriteResult>),
+    BatchReturn(Result<BatchResponse>),
+    RenameReturn(Result<WriteResult>),
+    CopyReturn(Result<WriteResult>),
+    BackupReturn(Result<Archive>),
+    RestoreReturn(Result<RestoreResult>),
+    ReplicateReturn(Result<()>)
 }
 
+/// channels of reads waiting for the result of the same read
+/// None once the result was sent and nobody can join anymore
+pub type Followers = Arc<Mutex<Option<Vec<Sender<FileTaskReturnType>>>>>;
+
 /// task to be used in queue
 /// result of executing a task is returned using the provided channel
 pub struct Task {
     task: FileTask,
     /// option giving the ability to take the sender end of the chan out of the struct
     chan: Option<Sender<FileTaskReturnType>>,
+    /// reads that joined this one, only set for reads
+    followers: Option<Followers>,
 }
 
 impl Task {
@@ -35,30 +72,248 @@ impl Task {
         Task {
             task,
             chan: Some(chan),
+            followers: None,
+        }
+    }
+
+    /// whoever pushed the task and every follower stopped waiting for the result, e.g. after a timeout
+    /// followers can't join anymore once the task is abandoned
+    pub fn abandoned(&self) -> bool {
+        let closed = self.chan.as_ref()
+            .map(|c| c.is_closed())
+            .unwrap_or(true);
+        if !closed {
+            return false;
+        }
+        let Some(followers) = self.followers.as_ref() else {
+            return true;
+        };
+        let Ok(mut followers) = followers.lock() else {
+            return true;
+        };
+        let abandoned = followers.as_ref()
+            .map(|f| f.iter().all(|c| c.is_closed()))
+            .unwrap_or(true);
+        if abandoned {
+            *followers = None;
+        }
+        abandoned
+    }
+
+    /// project of a read whose result can be shared with other reads of it
+    pub fn shared_read(&self) -> Option<&str> {
+        match &self.task {
+            FileTask::ReadConfig(project_name) => Some(project_name),
+            _ => None,
+        }
+    }
+
+    /// sets the reads that get the same result as this one
+    pub fn lead(&mut self, followers: Followers) {
+        self.followers = Some(followers);
+    }
+
+    /// sender end of the chan, None if it was already taken
+    pub fn take_chan(&mut self) -> Option<Sender<FileTaskReturnType>> {
+        take(&mut self.chan)
+    }
+
+    /// task touches every project so it can't run at the same time as any other task
+    pub fn exclusive(&self) -> bool {
+        matches!(self.task, FileTask::BackupConfig(_))
+    }
+
+    /// task doesn't change any project
+    pub fn read_only(&self) -> bool {
+        self.written_projects().is_empty()
+    }
+
+    /// name of the task used in metrics
+    pub fn kind(&self) -> &'static str {
+        match &self.task {
+            FileTask::CreateConfig(..) => "create",
+            FileTask::ReadConfig(..) => "read",
+            FileTask::ExistsConfig(..) => "exists",
+            FileTask::UpdateConfig(..) => "update",
+            FileTask::DeleteConfig(..) => "delete",
+            FileTask::SetSchemaConfig(..) => "set_schema",
+            FileTask::PutConfig(..) => "put",
+            FileTask::PatchConfig(..) => "patch",
+            FileTask::BatchConfig(..) => "batch",
+            FileTask::RenameConfig(..) => "rename",
+            FileTask::CopyConfig(..) => "copy",
+            FileTask::BackupConfig(..) => "backup",
+            FileTask::RestoreConfig(..) => "restore",
+            FileTask::ReplicateConfig(..) => "replicate",
+        }
+    }
+
+    /// names of the projects the task reads or changes, tasks sharing a project are executed in order
+    pub fn projects(&self) -> Vec<String> {
+        match &self.task {
+            FileTask::ReadConfig(project_name)
+                | FileTask::ExistsConfig(project_name) => vec![project_name.clone()],
+            FileTask::CopyConfig(project_name, target_name) => vec![project_name.clone(), target_name.clone()],
+            FileTask::BatchConfig(_, batch) => batch.operations.iter()
+                .map(|o| o.project_name().to_string())
+                .collect(),
+            _ => self.written_projects(),
+        }
+    }
+
+    /// names of the projects the task changes, empty for tasks that only read
+    pub fn written_projects(&self) -> Vec<String> {
+        match &self.task {
+            FileTask::ReadConfig(_)
+                | FileTask::ExistsConfig(_)
+                | FileTask::BackupConfig(_) => Vec::new(),
+            FileTask::CreateConfig(_, project)
+                | FileTask::UpdateConfig(_, project)
+                | FileTask::PutConfig(_, project) => vec![project.name.clone()],
+            FileTask::DeleteConfig(project_name)
+                | FileTask::PatchConfig(_, project_name, _)
+                | FileTask::ReplicateConfig(project_name, _) => vec![project_name.clone()],
+            FileTask::SetSchemaConfig(_, project_schema) => vec![project_schema.name.clone()],
+            FileTask::RenameConfig(project_name, target_name) => vec![project_name.clone(), target_name.clone()],
+            FileTask::CopyConfig(_, target_name) => vec![target_name.clone()],
+            FileTask::RestoreConfig(archive, _) => archive.entries.keys().cloned().collect(),
+            FileTask::BatchConfig(_, batch) => {
+                let mut names: Vec<String> = Vec::new();
+                for operation in batch.operations.iter().filter(|o| o.is_write()) {
+                    if !names.iter().any(|n| n == operation.project_name()) {
+                        names.push(operation.project_name().to_string());
+                    }
+                }
+                names
+            },
         }
     }
 
     /// function that executes the action based on the task type and returns the result using the channel
-    pub async fn execute(&mut self) {
+    /// successful changes are published to the watching clients
+    /// reads are served from the cache, entries written by the task are removed from it
+    pub async fn execute(&mut self, events: &Events, cache: &EntryCache) {
         // always Some
         let chan = take(&mut self.chan).unwrap();
-        match &self.task {
+
+        // old versions are only needed for the change events
+        let project_names = match events.listening() {
+            true => self.written_projects(),
+            false => Vec::new(),
+        };
+        let mut old = Vec::with_capacity(project_names.len());
+        for project_name in project_names {
+            let entry = read(&project_name).await.ok().flatten();
+            old.push((project_name, entry));
+        }
+
+        let (res, changed) = match &self.task {
             FileTask::CreateConfig(timestamp, project) => {
                 let res = create(*timestamp, project.clone()).await;
-                _ = chan.send(FileTaskReturnType::CreateReturn(res));
+                let changed = matches!(res, Ok(WriteResult::Done));
+                (FileTaskReturnType::CreateReturn(res), changed)
             },
             FileTask::ReadConfig(project_name) => {
-                let res = read(project_name).await;
-                _ = chan.send(FileTaskReturnType::ReadReturn(res));
+                let res = match cache.get(project_name) {
+                    Some(entry) => Ok(Some(entry)),
+                    None => read(project_name).await,
+                };
+                if let Ok(Some(entry)) = res.as_ref() {
+                    cache.insert(project_name, entry.clone());
+                }
+                (FileTaskReturnType::ReadReturn(res), false)
+            },
+            FileTask::ExistsConfig(project_name) => {
+                let res = match cache.get(project_name) {
+                    Some(entry) => Ok(Some(entry.timestamp)),
+                    None => version(project_name).await,
+                };
+                (FileTaskReturnType::ExistsReturn(res), false)
             },
             FileTask::UpdateConfig(timestamp, project) => {
                 let res = update(*timestamp, project.clone()).await;
-                _ = chan.send(FileTaskReturnType::UpdateReturn(res));
+                let changed = matches!(res, Ok(WriteResult::Done));
+                (FileTaskReturnType::UpdateReturn(res), changed)
             },
             FileTask::DeleteConfig(project_name) => {
                 let res = delete(project_name).await;
-                _ = chan.send(FileTaskReturnType::DeleteReturn(res));
+                let changed = matches!(res, Ok(true));
+                (FileTaskReturnType::DeleteReturn(res), changed)
+            },
+            FileTask::SetSchemaConfig(timestamp, project_schema) => {
+                let res = set_schema(*timestamp, project_schema.clone()).await;
+                let changed = matches!(res, Ok(WriteResult::Done));
+                (FileTaskReturnType::SetSchemaReturn(res), changed)
             },
+            FileTask::PutConfig(timestamp, project) => {
+                let res = put(*timestamp, project.clone()).await;
+                let changed = matches!(res, Ok((WriteResult::Done, _)));
+                (FileTaskReturnType::PutReturn(res), changed)
+            },
+            FileTask::PatchConfig(timestamp, project_name, project_patch) => {
+                let res = patch(*timestamp, project_name.clone(), project_patch.clone()).await;
+                let changed = matches!(res, Ok(WriteResult::Done));
+                (FileTaskReturnType::PatchReturn(res), changed)
+            },
+            FileTask::BatchConfig(timestamp, operations) => {
+                let res = batch(*timestamp, operations.clone()).await;
+                let changed = matches!(&res, Ok(res) if !res.rolled_back);
+                (FileTaskReturnType::BatchReturn(res), changed)
+            },
+            FileTask::RenameConfig(project_name, target_name) => {
+                let res = rename(project_name, target_name).await;
+                let changed = matches!(res, Ok(WriteResult::Done));
+                (FileTaskReturnType::RenameReturn(res), changed)
+            },
+            FileTask::CopyConfig(project_name, target_name) => {
+                let res = copy(project_name, target_name).await;
+                let changed = matches!(res, Ok(WriteResult::Done));
+                (FileTaskReturnType::CopyReturn(res), changed)
+            },
+            FileTask::BackupConfig(timestamp) => {
+                let res = backup(*timestamp).await;
+                (FileTaskReturnType::BackupReturn(res), false)
+            },
+            FileTask::RestoreConfig(archive, policy) => {
+                let res = restore(archive, *policy).await;
+                let changed = matches!(res, Ok(RestoreResult::Done(_)));
+                (FileTaskReturnType::RestoreReturn(res), changed)
+            },
+            FileTask::ReplicateConfig(project_name, entry) => {
+                let res = replicate(project_name, entry.as_ref()).await;
+                let changed = res.is_ok();
+                (FileTaskReturnType::ReplicateReturn(res), changed)
+            },
+        };
+
+        // failed writes can still leave a changed file behind, e.g. an undone batch
+        for project_name in self.written_projects() {
+            cache.invalidate(&project_name);
+        }
+
+        if changed {
+            for (project_name, old) in old {
+                let new = read(&project_name).await.ok().flatten();
+                // batches can contain operations that failed or changed nothing
+                if old != new {
+                    events.publish(&project_name, timestamp(), old.as_ref(), new.as_ref());
+                }
+            }
+        }
+        // followers are taken out so nobody joins after the result is sent
+        let followers = self.followers.as_ref()
+            .and_then(|f| f.lock().ok()?.take())
+            .unwrap_or_default();
+        if let FileTaskReturnType::ReadReturn(res) = &res {
+            for follower in followers {
+                // errors can't be cloned, only the message is shared
+                let res = match res {
+                    Ok(entry) => Ok(entry.clone()),
+                    Err(err) => Err(anyhow!("{}", err)),
+                };
+                _ = follower.send(FileTaskReturnType::ReadReturn(res));
+            }
         }
+        _ = chan.send(res);
     }
 }
@@ -0,0 +1,221 @@
+use std::{sync::Arc, time::Duration};
+use anyhow::{Result, Context, anyhow};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, header};
+use tokio::time::{sleep, timeout};
+use url::Url;
+use senvy_common::{
+    types::{Archive, ChangeEvent, ProjectEntry},
+    sse::EventParser
+};
+use crate::{
+    queue::{FileTaskQueue, FileTask, task::FileTaskReturnType},
+    handlers::timestamp
+};
+
+/// primary sends a keep-alive every 15s, stream is considered dead after missing a few of them
+const IDLE_TIMEOUT: Duration = Duration::from_secs(45);
+/// first wait before reconnecting to the primary, doubled on every failed attempt
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// primary rejected a request of the replica, it can be sent again after the wait
+#[derive(Debug)]
+struct RateLimited(Duration);
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rate limited by the primary, retrying in {}s", self.0.as_secs())
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// keeps the entries of a read-only server the same as the entries of its primary
+/// changes are taken from the change stream of the primary
+/// every entry is compared with the primary on connecting and after missing changes
+pub struct Replica {
+    primary: Url,
+    /// admin token of the primary, needed for its backup and exempting the replica from rate limits
+    token: String,
+    client: Client,
+    /// client without a total timeout, used only for the change stream
+    stream_client: Client,
+    queue: Arc<FileTaskQueue>,
+}
+
+impl Replica {
+    pub fn new(primary: Url, token: String, queue: Arc<FileTaskQueue>) -> Result<Self> {
+        let client = Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(60))
+            .build()
+            .context("building reqwest client")?;
+        let stream_client = Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .context("building reqwest client")?;
+        Ok(Replica {
+            primary,
+            token,
+            client,
+            stream_client,
+            queue,
+        })
+    }
+
+    /// follows the primary until the task is dropped, reconnecting whenever the stream ends
+    pub async fn follow(&self) {
+        let mut delay = RETRY_DELAY;
+        loop {
+            match self.stream(&mut delay).await {
+                Ok(()) => log::warn!("Primary closed the change stream"),
+                Err(err) => {
+                    log::warn!("Error following the primary: {:#}", err);
+                    // primary decides how long a rate limited replica waits
+                    if let Some(RateLimited(wait)) = err.downcast_ref() {
+                        delay = (*wait).max(delay);
+                    }
+                },
+            }
+            sleep(delay).await;
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+        }
+    }
+
+    /// applies changes from the stream until it ends, delay is reset once the entries are synced
+    async fn stream(&self, delay: &mut Duration) -> Result<()> {
+        let req = self.stream_client.get(self.endpoint(&["v1", "events"])?)
+            .header("Accept", "text/event-stream");
+        let mut res = self.send(req)
+            .await
+            .context("connecting to the change stream")?;
+        if res.status() != StatusCode::OK {
+            return Err(anyhow!("primary responded with {}", res.status()))
+                .context("connecting to the change stream");
+        }
+
+        // changes made before connecting are not in the stream
+        self.sync().await?;
+        *delay = RETRY_DELAY;
+
+        let mut parser = EventParser::default();
+        loop {
+            let chunk = timeout(IDLE_TIMEOUT, res.chunk())
+                .await
+                .map_err(|_| anyhow!("no data from the primary for {}s", IDLE_TIMEOUT.as_secs()))?
+                .context("reading the change stream")?;
+            let Some(chunk) = chunk else {
+                return Ok(());
+            };
+            for event in parser.push(&chunk) {
+                match event.name.as_str() {
+                    "change" => {
+                        let change: ChangeEvent = serde_json::from_str(&event.data)
+                            .context("parsing change event")?;
+                        // entry is read again so an older change can't overwrite a newer one
+                        let entry = self.pull(&change.project).await?;
+                        self.replicate(change.project, entry).await?;
+                    },
+                    "lagged" => {
+                        log::warn!("Missed {} changes from the primary, syncing every entry", event.data);
+                        self.sync().await?;
+                    },
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    /// makes every entry the same as on the primary
+    async fn sync(&self) -> Result<()> {
+        let req = self.client.get(self.endpoint(&["v1", "admin", "backup"])?);
+        let res = self.send(req)
+            .await
+            .context("requesting backup from the primary")?;
+        if res.status() != StatusCode::OK {
+            return Err(anyhow!("primary responded with {}", res.status()))
+                .context("requesting backup from the primary");
+        }
+        let body = res.text().await
+            .context("reading backup of the primary")?;
+        let primary: Archive = serde_json::from_str(&body)
+            .context("parsing backup of the primary")?;
+
+        let local = match self.execute(FileTask::BackupConfig(timestamp())).await? {
+            FileTaskReturnType::BackupReturn(res) => res.context("reading local entries")?,
+            _ => unreachable!(),
+        };
+
+        let mut changed = 0;
+        for (project_name, entry) in primary.entries.iter() {
+            if local.entries.get(project_name) != Some(entry) {
+                self.replicate(project_name.clone(), Some(entry.clone())).await?;
+                changed += 1;
+            }
+        }
+        for project_name in local.entries.keys() {
+            if !primary.entries.contains_key(project_name) {
+                self.replicate(project_name.clone(), None).await?;
+                changed += 1;
+            }
+        }
+        log::info!("Synced entries with the primary, {} of them changed", changed);
+        Ok(())
+    }
+
+    /// current entry on the primary, None means that it doesn't exist
+    async fn pull(&self, project_name: &str) -> Result<Option<ProjectEntry>> {
+        let req = self.client.get(self.endpoint(&["v1", "projects", project_name])?);
+        let res = self.send(req)
+            .await
+            .context("reading entry from the primary")?;
+        match res.status() {
+            StatusCode::OK => {
+                let body = res.text().await
+                    .context("reading entry of the primary")?;
+                let entry = serde_json::from_str(&body)
+                    .context("parsing entry of the primary")?;
+                Ok(Some(entry))
+            },
+            StatusCode::NOT_FOUND => Ok(None),
+            status => Err(anyhow!("primary responded with {}", status))
+                .context("reading entry from the primary"),
+        }
+    }
+
+    async fn replicate(&self, project_name: String, entry: Option<ProjectEntry>) -> Result<()> {
+        match self.execute(FileTask::ReplicateConfig(project_name, entry)).await? {
+            FileTaskReturnType::ReplicateReturn(res) => res.context("writing entry taken from the primary"),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn execute(&self, task: FileTask) -> Result<FileTaskReturnType> {
+        self.queue.execute_replicated(task)
+            .await
+            .map_err(|err| anyhow!("{}", err))
+    }
+
+    /// sends the request with the admin token, rate limited response is an error holding the wait
+    async fn send(&self, req: RequestBuilder) -> Result<Response> {
+        let res = req.bearer_auth(&self.token).send().await?;
+        if res.status() == StatusCode::TOO_MANY_REQUESTS {
+            let wait = res.headers()
+                .get(header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok()?.parse::<u64>().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(RETRY_DELAY);
+            return Err(RateLimited(wait.min(MAX_RETRY_DELAY)).into());
+        }
+        Ok(res)
+    }
+
+    fn endpoint(&self, segments: &[&str]) -> Result<Url> {
+        let mut endpoint = self.primary.clone();
+        endpoint.path_segments_mut()
+            .map_err(|_| anyhow!("primary url can't have a path"))?
+            .clear()
+            .extend(segments);
+        Ok(endpoint)
+    }
+}